@@ -1,18 +1,23 @@
-mod models;
+use std::time::Duration;
 
-use models::db::{DataBase, Entry, EntryListItem, VaultError};
+use vault_core::audit::{AuditOptions, AuditReport};
+use vault_core::autobackup::{BackupSchedule, BackupStatus, StoredBackup};
+use vault_core::backup::BackupReport;
+use vault_core::breach::{self, BreachReport};
+use vault_core::clipboard::{SecureClipboard, SystemClipboard, DEFAULT_CLEAR_AFTER};
+use vault_core::db::{DataBase, EntryField, EntryListItem, EntryView, ImportSummary, VaultError};
+use vault_core::export::ExportFormat;
+use vault_core::generator::{GeneratedPassword, GeneratorOptions};
+use vault_core::passphrase::PassphraseOptions;
+use vault_core::profiles::GeneratorProfile;
+use vault_core::query::{EntryPage, ListQuery, SortKey};
+use vault_core::rotation::RotationReminder;
+use vault_core::search::{SearchOptions, SearchResults};
+use vault_core::strength::StrengthReport;
 use secrecy::SecretString;
 use tauri::State;
 use tauri::Manager;
-
-fn err_ui(e: VaultError) -> String {
-    match e {
-        VaultError::Locked => "Хранилище заблокировано — разблокируй мастер-паролем".into(),
-        VaultError::BadMasterPassword => "Неверный мастер-пароль или чужой бэкап".into(),
-        VaultError::NotInitialized => "Хранилище не инициализировано".into(),
-        other => other.to_string(),
-    }
-}
+use tauri::{AppHandle, Emitter};
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -20,26 +25,88 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-async fn vault_init(db: State<'_, DataBase>, master: String) -> Result<(), String> {
-    db.init_master(SecretString::new(master)).await.map_err(err_ui)
+async fn vault_init(db: State<'_, DataBase>, master: String) -> Result<(), VaultError> {
+    db.init_master(SecretString::new(master)).await
 }
 
+/// За сколько дней до истечения срока ротации напоминать при разблокировке.
+const ROTATION_NOTICE_DAYS: u32 = 14;
+
 #[tauri::command]
-async fn vault_unlock(db: State<'_, DataBase>, master: String) -> Result<(), String> {
-    db.unlock(SecretString::new(master)).await.map_err(err_ui)
+async fn vault_unlock(
+    app: AppHandle,
+    db: State<'_, DataBase>,
+    master: String,
+) -> Result<(), VaultError> {
+    db.unlock(SecretString::new(master)).await?;
+    // хранилище уже открыто: сбой напоминаний не делает разблокировку неудачной —
+    // он уходит в UI отдельным событием, а неудачная отправка событий игнорируется
+    match db.rotation_due(ROTATION_NOTICE_DAYS).await {
+        Ok(due) if due.is_empty() => {}
+        Ok(due) => {
+            let _ = app.emit("rotation-reminders", &due);
+        }
+        Err(e) => {
+            let _ = app.emit("rotation-reminders-failed", &e);
+        }
+    }
+    Ok(())
 }
 
 #[tauri::command]
-async fn vault_lock(db: State<'_, DataBase>) -> Result<(), String> {
+async fn vault_lock(db: State<'_, DataBase>, clip: State<'_, Clipboard>) -> Result<(), VaultError> {
     db.lock().await;
+    // скопированный секрет не должен пережить блокировку
+    if let Some(c) = &clip.0 {
+        c.clear_now();
+    }
     Ok(())
 }
 
+/// Буфер обмена; `None`, если система его не дала (например, без графической сессии).
+struct Clipboard(Option<SecureClipboard>);
+
+/// Копирует поле записи в системный буфер, не отправляя значение в webview.
+/// Буфер очищается через `clear_after_secs` (по умолчанию 20 с), если в нём всё ещё это значение.
+#[tauri::command]
+async fn copy_secret(
+    db: State<'_, DataBase>,
+    clip: State<'_, Clipboard>,
+    id: i64,
+    field: EntryField,
+    clear_after_secs: Option<u64>,
+) -> Result<(), VaultError> {
+    let clip = clip
+        .0
+        .as_ref()
+        .ok_or_else(|| VaultError::Other("clipboard is not available".into()))?;
+    let value = db.entry_field(id, field).await?;
+    let clear_after = clear_after_secs.map_or(DEFAULT_CLEAR_AFTER, Duration::from_secs);
+    clip.copy(&value, clear_after)
+}
+
 #[tauri::command]
-async fn vault_is_unlocked(db: State<'_, DataBase>) -> Result<bool, String> {
+async fn vault_is_unlocked(db: State<'_, DataBase>) -> Result<bool, VaultError> {
     Ok(db.is_unlocked().await)
 }
 
+#[tauri::command]
+async fn entry_set_rotation(
+    db: State<'_, DataBase>,
+    id: i64,
+    rotate_days: Option<u32>,
+) -> Result<(), VaultError> {
+    db.set_entry_rotation(id, rotate_days).await
+}
+
+#[tauri::command]
+async fn rotation_due(
+    db: State<'_, DataBase>,
+    within_days: Option<u32>,
+) -> Result<Vec<RotationReminder>, VaultError> {
+    db.rotation_due(within_days.unwrap_or(ROTATION_NOTICE_DAYS)).await
+}
+
 #[tauri::command]
 async fn add_entry(
     db: State<'_, DataBase>,
@@ -47,87 +114,282 @@ async fn add_entry(
     username: String,
     password: String,
     notes: Option<String>,
-) -> Result<i64, String> {
-    db.add_entry(&site, &username, &password, notes.as_deref())
-        .await
-        .map_err(err_ui)
+) -> Result<i64, VaultError> {
+    db.add_entry(&site, &username, &password, notes.as_deref()).await
 }
 
+/// Запись без пароля и заметок — их webview получает только через `reveal_field`.
 #[tauri::command]
-async fn get_entry(db: State<'_, DataBase>, id: i64) -> Result<Entry, String> {
-    db.get_entry(id).await.map_err(err_ui)
+async fn get_entry(db: State<'_, DataBase>, id: i64) -> Result<EntryView, VaultError> {
+    db.entry_view(id).await
 }
 
+/// Расшифровывает одно поле по явной просьбе пользователя (показ считается использованием).
+#[tauri::command]
+async fn reveal_field(
+    db: State<'_, DataBase>,
+    id: i64,
+    field: EntryField,
+) -> Result<String, VaultError> {
+    Ok(std::mem::take(&mut *db.entry_field(id, field).await?))
+}
+
+/// `sort` важнее `weakest_first`. Сайт, логин и стойкость идут по возрастанию,
+/// даты и использование — от свежих и частых.
 #[tauri::command]
 async fn list_entries(
     db: State<'_, DataBase>,
     search: Option<String>,
-) -> Result<Vec<EntryListItem>, String> {
-    db.list_entries(search.as_deref()).await.map_err(err_ui)
+    weakest_first: Option<bool>,
+    favorites_first: Option<bool>,
+    sort: Option<SortKey>,
+) -> Result<Vec<EntryListItem>, VaultError> {
+    let sort = sort.unwrap_or(if weakest_first.unwrap_or(false) {
+        SortKey::Strength
+    } else {
+        SortKey::Updated
+    });
+    let query = ListQuery {
+        search,
+        sort,
+        descending: !matches!(sort, SortKey::Site | SortKey::Username | SortKey::Strength),
+        favorites_first: favorites_first.unwrap_or(false),
+        ..Default::default()
+    };
+    Ok(db.query_entries(&query).await?.items)
+}
+
+#[tauri::command]
+async fn entry_set_favorite(
+    db: State<'_, DataBase>,
+    id: i64,
+    favorite: bool,
+) -> Result<(), VaultError> {
+    db.set_favorite(id, favorite).await
+}
+
+/// `ids` — все закреплённые записи в новом порядке.
+#[tauri::command]
+async fn entry_set_pins(db: State<'_, DataBase>, ids: Vec<i64>) -> Result<(), VaultError> {
+    db.set_pins(&ids).await
 }
 
+#[tauri::command]
+async fn query_entries(db: State<'_, DataBase>, query: ListQuery) -> Result<EntryPage, VaultError> {
+    db.query_entries(&query).await
+}
+
+#[tauri::command]
+async fn search_entries(
+    db: State<'_, DataBase>,
+    options: SearchOptions,
+) -> Result<SearchResults, VaultError> {
+    db.search_entries(&options).await
+}
+
+#[tauri::command]
+async fn estimate_strength(
+    db: State<'_, DataBase>,
+    password: String,
+    site: Option<String>,
+    username: Option<String>,
+) -> Result<StrengthReport, VaultError> {
+    Ok(db.estimate_strength(&password, site.as_deref(), username.as_deref()))
+}
+
+#[tauri::command]
+async fn vault_audit(
+    db: State<'_, DataBase>,
+    stale_after_days: Option<u32>,
+    weak_below: Option<u8>,
+) -> Result<AuditReport, VaultError> {
+    let defaults = AuditOptions::default();
+    let opts = AuditOptions {
+        stale_after_days: stale_after_days.unwrap_or(defaults.stale_after_days),
+        weak_below: weak_below.unwrap_or(defaults.weak_below),
+    };
+    db.vault_audit(&opts).await
+}
+
+#[tauri::command]
+async fn breach_check(db: State<'_, DataBase>, dataset: String) -> Result<BreachReport, VaultError> {
+    db.check_breaches(dataset).await
+}
+
+/// Сборка индекса читает весь файл HIBP (десятки ГБ), поэтому в отдельном потоке.
+#[tauri::command]
+async fn breach_build_index(source: String, index: String) -> Result<u64, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        breach::build_index(source.as_ref(), index.as_ref())
+    })
+    .await
+    .map_err(|e| VaultError::Other(e.to_string()))?
+}
+
+/// `revision` — ревизия открытой в форме записи; при чужой правке вернётся `conflict`.
 #[tauri::command]
 async fn update_entry(
     db: State<'_, DataBase>,
     id: i64,
+    revision: i64,
     site: String,
     username: String,
     password: Option<String>,
     notes: Option<String>,
-) -> Result<(), String> {
-    db.update_entry(id, &site, &username, password.as_deref(), notes.as_deref())
+) -> Result<i64, VaultError> {
+    db.update_entry(id, revision, &site, &username, password.as_deref(), notes.as_deref())
         .await
-        .map_err(err_ui)
 }
 
 #[tauri::command]
-async fn delete_entry(db: State<'_, DataBase>, id: i64) -> Result<(), String> {
-    db.delete_entry(id).await.map_err(err_ui)
+async fn delete_entry(db: State<'_, DataBase>, id: i64) -> Result<(), VaultError> {
+    db.delete_entry(id).await
 }
 
 #[tauri::command]
 async fn generate_password(
     db: State<'_, DataBase>,
-    length: usize,
-    use_digits: bool,
-    use_upper: bool,
-    use_symbols: bool,
-) -> Result<String, String> {
-    let s = db.generate_password(length, use_digits, use_upper, use_symbols);
-    Ok(s)
+    options: Option<GeneratorOptions>,
+    entry_id: Option<i64>,
+    site: Option<String>,
+) -> Result<GeneratedPassword, VaultError> {
+    db.generate_password(&options.unwrap_or_default(), entry_id, site.as_deref()).await
 }
 
 #[tauri::command]
-async fn export_backup(db: State<'_, DataBase>, path: String) -> Result<(), String> {
-    db.export_encrypted_backup(path).await.map_err(err_ui)
+async fn profile_list(db: State<'_, DataBase>) -> Result<Vec<GeneratorProfile>, VaultError> {
+    db.list_profiles().await
 }
 
 #[tauri::command]
-async fn import_backup(db: State<'_, DataBase>, path: String) -> Result<usize, String> {
-    db.import_encrypted_backup(path).await.map_err(err_ui)
+async fn profile_save(db: State<'_, DataBase>, profile: GeneratorProfile) -> Result<i64, VaultError> {
+    db.save_profile(profile).await
 }
 
-// Совместимость со старым фронтом:
 #[tauri::command]
-async fn add_password(db: State<'_, DataBase>, password: String) -> Result<i64, String> {
-    db.add_entry("example.com", "user", &password, None)
-        .await
-        .map_err(err_ui)
+async fn profile_delete(db: State<'_, DataBase>, id: i64) -> Result<(), VaultError> {
+    db.delete_profile(id).await
+}
+
+#[tauri::command]
+async fn entry_set_profile(
+    db: State<'_, DataBase>,
+    id: i64,
+    profile_id: Option<i64>,
+) -> Result<(), VaultError> {
+    db.set_entry_profile(id, profile_id).await
+}
+
+#[tauri::command]
+async fn generate_passphrase(
+    db: State<'_, DataBase>,
+    options: Option<PassphraseOptions>,
+) -> Result<GeneratedPassword, VaultError> {
+    db.generate_passphrase(&options.unwrap_or_default())
+}
+
+#[tauri::command]
+async fn export_backup(
+    db: State<'_, DataBase>,
+    path: String,
+    passphrase: String,
+) -> Result<(), VaultError> {
+    db.export_encrypted_backup(path, &SecretString::new(passphrase)).await
+}
+
+#[tauri::command]
+async fn import_backup(
+    db: State<'_, DataBase>,
+    path: String,
+    passphrase: String,
+) -> Result<ImportSummary, VaultError> {
+    db.import_encrypted_backup(path, &SecretString::new(passphrase)).await
+}
+
+#[tauri::command]
+async fn export_plaintext(
+    db: State<'_, DataBase>,
+    path: String,
+    master: String,
+    format: ExportFormat,
+) -> Result<usize, VaultError> {
+    db.export_plaintext(path, &SecretString::new(master), format).await
+}
+
+#[tauri::command]
+async fn verify_backup(
+    db: State<'_, DataBase>,
+    path: String,
+    passphrase: Option<String>,
+) -> Result<BackupReport, VaultError> {
+    db.verify_backup(path, passphrase.map(SecretString::new).as_ref()).await
+}
+
+#[tauri::command]
+async fn backup_schedule_get(db: State<'_, DataBase>) -> Result<Option<BackupSchedule>, VaultError> {
+    db.backup_schedule().await
+}
+
+#[tauri::command]
+async fn backup_status(db: State<'_, DataBase>) -> Result<Option<BackupStatus>, VaultError> {
+    db.backup_status().await
 }
 
 #[tauri::command]
-async fn get_password(db: State<'_, DataBase>, id: i64) -> Result<String, String> {
-    db.get_password(id).await.map_err(err_ui)
+async fn backup_schedule_set(
+    db: State<'_, DataBase>,
+    schedule: BackupSchedule,
+    passphrase: String,
+) -> Result<(), VaultError> {
+    db.set_backup_schedule(schedule, &SecretString::new(passphrase)).await
+}
+
+#[tauri::command]
+async fn backup_schedule_clear(db: State<'_, DataBase>) -> Result<(), VaultError> {
+    db.clear_backup_schedule().await
+}
+
+#[tauri::command]
+async fn backup_run_now(db: State<'_, DataBase>) -> Result<StoredBackup, VaultError> {
+    db.run_scheduled_backup().await
+}
+
+#[tauri::command]
+async fn backup_list(db: State<'_, DataBase>) -> Result<Vec<StoredBackup>, VaultError> {
+    db.list_stored_backups().await
+}
+
+#[tauri::command]
+async fn backup_verify(
+    db: State<'_, DataBase>,
+    file_name: String,
+) -> Result<BackupReport, VaultError> {
+    db.verify_stored_backup(&file_name).await
+}
+
+#[tauri::command]
+async fn backup_restore(
+    db: State<'_, DataBase>,
+    file_name: String,
+) -> Result<ImportSummary, VaultError> {
+    db.restore_stored_backup(&file_name).await
 }
 
 #[tauri::command]
-async fn export_backup_bytes(db: tauri::State<'_, DataBase>) -> Result<Vec<u8>, String> {
-    db.export_encrypted_bytes().await.map_err(err_ui)
+async fn export_backup_bytes(
+    db: tauri::State<'_, DataBase>,
+    passphrase: String,
+) -> Result<Vec<u8>, VaultError> {
+    db.export_encrypted_bytes(&SecretString::new(passphrase)).await
 }
 
 #[tauri::command]
-async fn import_backup_bytes(db: tauri::State<'_, DataBase>, data: Vec<u8>) -> Result<usize, String> {
-    db.import_encrypted_bytes(&data).await.map_err(err_ui)
+async fn import_backup_bytes(
+    db: tauri::State<'_, DataBase>,
+    data: Vec<u8>,
+    passphrase: String,
+) -> Result<ImportSummary, VaultError> {
+    db.import_encrypted_bytes(&data, &SecretString::new(passphrase)).await
 }
 
 
@@ -139,16 +401,23 @@ pub fn run() {
         .setup(|app| {
             let db = tauri::async_runtime::block_on(DataBase::open("app.db"))
                 .map_err(|e| anyhow::anyhow!(e))?;
+            tauri::async_runtime::spawn(db.clone().run_backup_scheduler(Duration::from_secs(60)));
             app.manage(db);
+            app.manage(Clipboard(SystemClipboard::new().ok().map(SecureClipboard::new)));
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             greet,
-            vault_init, vault_unlock, vault_lock, vault_is_unlocked,
-            add_entry, get_entry, list_entries, update_entry, delete_entry,
-            generate_password,
+            vault_init, vault_unlock, vault_lock, vault_is_unlocked, copy_secret,
+            add_entry, get_entry, reveal_field, list_entries, query_entries, search_entries, entry_set_favorite, entry_set_pins, update_entry, delete_entry,
+            entry_set_rotation, rotation_due,
+            generate_password, generate_passphrase, estimate_strength,
+            profile_list, profile_save, profile_delete, entry_set_profile,
+            vault_audit, breach_check, breach_build_index,
             export_backup, import_backup, import_backup_bytes, export_backup_bytes,
-            add_password, get_password
+            verify_backup, export_plaintext,
+            backup_schedule_get, backup_schedule_set, backup_schedule_clear, backup_status,
+            backup_run_now, backup_list, backup_verify, backup_restore
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,28 +1,98 @@
 import { useEffect, useMemo, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open, save } from "@tauri-apps/plugin-dialog";
 import { readFile, writeFile } from "@tauri-apps/plugin-fs";
 
+import { errorCode, errorText } from "./errors";
 import "./App.css";
 
 type EntryListItem = {
   id: number;
   site: string;
   username: string;
+  strength?: number | null;
   created_at: number;
   updated_at: number;
+  revision: number;
+  last_used_at?: number | null;
+  use_count: number;
+  favorite: boolean;
+  pin_order?: number | null;
 };
 
-type Entry = {
-  id: number;
-  site: string;
-  username: string;
-  password: string;
-  notes?: string | null;
-  created_at: number;
-  updated_at: number;
+type SortKey =
+  | "updated"
+  | "created"
+  | "site"
+  | "username"
+  | "strength"
+  | "last_used"
+  | "use_count";
+
+type EntryPage = { total: number; items: EntryListItem[] };
+
+type SearchResults = {
+  total: number;
+  hits: { entry: EntryListItem; field: string; kind: string; score: number }[];
+};
+
+type StrengthReport = {
+  score: number;
+  entropy_bits: number;
+  warning?: string | null;
+  suggestions: string[];
+};
+
+const STRENGTH_LABELS = ["very weak", "weak", "fair", "strong", "very strong"];
+
+type AuditEntryRef = { id: number; site: string; username: string };
+
+type AuditReport = {
+  total: number;
+  reused: AuditEntryRef[][];
+  weak: { entry: AuditEntryRef; score: number; warning?: string | null }[];
+  stale: { entry: AuditEntryRef; age_days: number }[];
+  missing_username: AuditEntryRef[];
+  duplicates: AuditEntryRef[][];
 };
 
+type RotationReminder = {
+  entry: AuditEntryRef;
+  rotate_days: number;
+  expires_at: number;
+  days_left: number;
+  expired: boolean;
+};
+
+type GeneratedPassword = { password: string; entropy_bits: number };
+
+type BreachReport = {
+  checked: number;
+  breached: { entry: AuditEntryRef; count: number }[];
+};
+
+type ImportSummary = {
+  inserted: number;
+  updated: number;
+  skipped: number;
+};
+
+type BackupReport = {
+  format_version: number;
+  created_at?: number | null;
+  entry_count: number;
+  problems: string[];
+};
+
+// get_entry отдаёт запись без секретов; пароль и заметки — только через reveal_field
+type EntryView = EntryListItem & { has_notes: boolean };
+
+type Revealed = { password?: string; notes?: string };
+
+const PAGE_SIZE = 200;
+const CLIPBOARD_CLEAR_SECS = 20;
+
 function fmt(ts: number) {
   const d = new Date(ts * 1000);
   return d.toLocaleString();
@@ -32,9 +102,8 @@ function fmt(ts: number) {
 async function call<T>(cmd: string, args?: any): Promise<T> {
   try {
     return await invoke<T>(cmd, args);
-  } catch (e: any) {
-    const msg = typeof e === "string" ? e : e?.message ?? JSON.stringify(e);
-    alert(`Error: ${msg}`);
+  } catch (e: unknown) {
+    alert(`Error: ${errorText(e)}`);
     throw e;
   }
 }
@@ -44,23 +113,55 @@ export default function App() {
   const [master, setMaster] = useState("");
   const [search, setSearch] = useState("");
   const [items, setItems] = useState<EntryListItem[]>([]);
-  const [selected, setSelected] = useState<Entry | null>(null);
+  const [selected, setSelected] = useState<EntryView | null>(null);
+  const [revealed, setRevealed] = useState<Revealed>({});
 
   // контролируемые поля для генерации паролей
   const [addPwd, setAddPwd] = useState("");
   const [editPwd, setEditPwd] = useState("");
+  const [weakestFirst, setWeakestFirst] = useState(false);
+  const [searchNotes, setSearchNotes] = useState(false);
+  const [sortKey, setSortKey] = useState<SortKey>("updated");
+  const [total, setTotal] = useState(0);
+  const [addStrength, setAddStrength] = useState<StrengthReport | null>(null);
 
-  const reload = async () => {
+  useEffect(() => {
+    if (!addPwd) {
+      setAddStrength(null);
+      return;
+    }
+    invoke<StrengthReport>("estimate_strength", { password: addPwd })
+      .then(setAddStrength)
+      .catch(() => setAddStrength(null));
+  }, [addPwd]);
+
+  const reload = async (weakest = weakestFirst, sort = sortKey) => {
     const ok = await call<boolean>("vault_is_unlocked");
     setUnlocked(ok);
-    if (ok) {
-      const list = await call<EntryListItem[]>("list_entries", {
-        search: search.trim() ? search : null,
+    if (ok && search.trim()) {
+      // поиск ранжирует сам: точный сайт, начало слова, опечатки
+      const res = await call<SearchResults>("search_entries", {
+        options: { query: search, in_notes: searchNotes, limit: 200 },
       });
-      setItems(list);
+      setItems(res.hits.map((h) => h.entry));
+      setTotal(res.total);
+    } else if (ok) {
+      const key = weakest ? "strength" : sort;
+      const page = await call<EntryPage>("query_entries", {
+        query: {
+          sort: key,
+          // по алфавиту и по слабости — по возрастанию, остальное — свежие и частые сверху
+          descending: key !== "site" && key !== "username" && key !== "strength",
+          favorites_first: true,
+          limit: PAGE_SIZE,
+        },
+      });
+      setItems(page.items);
+      setTotal(page.total);
     } else {
       setItems([]);
       setSelected(null);
+      setRevealed({});
     }
   };
 
@@ -68,15 +169,36 @@ export default function App() {
     reload();
   }, []);
 
+  // бэкенд присылает напоминания о ротации паролей при разблокировке
+  useEffect(() => {
+    const unlisten = listen<RotationReminder[]>("rotation-reminders", ({ payload }) => {
+      const lines = payload.map((r) => {
+        const who = r.entry.username ? `${r.entry.site} (${r.entry.username})` : r.entry.site;
+        return r.expired
+          ? `${who}: expired ${-r.days_left} days ago`
+          : `${who}: expires in ${r.days_left} days`;
+      });
+      alert(`Time to change these passwords:\n${lines.join("\n")}`);
+    });
+    // хранилище при этом открыто — это только предупреждение
+    const unlistenFailed = listen<unknown>("rotation-reminders-failed", ({ payload }) => {
+      console.warn(`Rotation reminders are unavailable: ${errorText(payload)}`);
+    });
+    return () => {
+      unlisten.then((f) => f());
+      unlistenFailed.then((f) => f());
+    };
+  }, []);
+
   const handleInit = async () => {
     if (!master.trim()) return;
     try {
-      await call("vault_init", { master });
-    } catch (e: any) {
-      const msg = String(typeof e === "string" ? e : e?.message ?? "");
-      if (msg.toLowerCase().includes("already initialized")) {
+      await invoke("vault_init", { master });
+    } catch (e: unknown) {
+      if (errorCode(e) === "already_initialized") {
         await call("vault_unlock", { master });
       } else {
+        alert(`Error: ${errorText(e)}`);
         throw e;
       }
     }
@@ -114,16 +236,58 @@ export default function App() {
     setSearch("");
     await reload();
     // auto-open
-    const ent = await call<Entry>("get_entry", { id });
-    setSelected(ent);
+    await openEntry(id);
   };
 
   const openEntry = async (id: number) => {
-    const ent = await call<Entry>("get_entry", { id });
+    const ent = await call<EntryView>("get_entry", { id });
     setSelected(ent);
+    setRevealed({}); // раскрытые секреты прошлой записи забываем
     setEditPwd(""); // сбрасываем поле нового пароля
   };
 
+  // секрет расшифровывается и попадает в webview только по нажатию
+  const reveal = async (id: number, field: "password" | "notes") => {
+    const value = await call<string>("reveal_field", { id, field });
+    setRevealed((r) => ({ ...r, [field]: value }));
+  };
+
+  // значение копирует бэкенд, в webview оно не попадает; буфер очистится сам
+  const copySecret = async (id: number, field: "password" | "username") => {
+    await call("copy_secret", { id, field, clearAfterSecs: CLIPBOARD_CLEAR_SECS });
+    alert(`Copied ${field}; the clipboard is cleared in ${CLIPBOARD_CLEAR_SECS} s`);
+  };
+
+  const toggleFavorite = async (it: EntryListItem) => {
+    await call("entry_set_favorite", { id: it.id, favorite: !it.favorite });
+    await reload();
+  };
+
+  // все закреплённые по порядку; список на экране может быть отфильтрован поиском
+  const pinnedIds = async () => {
+    const page = await call<EntryPage>("query_entries", {
+      query: { favorites_first: true },
+    });
+    return page.items.filter((i) => i.pin_order != null).map((i) => i.id);
+  };
+
+  const togglePin = async (it: EntryListItem) => {
+    const ids = await pinnedIds();
+    await call("entry_set_pins", {
+      ids: it.pin_order != null ? ids.filter((id) => id !== it.id) : [...ids, it.id],
+    });
+    await reload();
+  };
+
+  const movePinUp = async (it: EntryListItem) => {
+    const ids = await pinnedIds();
+    const i = ids.indexOf(it.id);
+    if (i <= 0) return;
+    [ids[i - 1], ids[i]] = [ids[i], ids[i - 1]];
+    await call("entry_set_pins", { ids });
+    await reload();
+  };
+
   const handleDelete = async (id: number) => {
     if (!confirm("Delete entry?")) return;
     await call("delete_entry", { id });
@@ -132,7 +296,8 @@ export default function App() {
 
   const handleUpdate = async (
     e: React.FormEvent<HTMLFormElement>,
-    id: number
+    id: number,
+    revision: number
   ) => {
     e.preventDefault();
     const fd = new FormData(e.currentTarget);
@@ -140,17 +305,23 @@ export default function App() {
     const username = String(fd.get("username") || "");
     const password = String(fd.get("password") || "");
     const notes = String(fd.get("notes") || "");
-    await call("update_entry", {
-      id,
-      site,
-      username,
-      password: password ? password : null,
-      notes: notes.length ? notes : null,
-    });
+    try {
+      await call("update_entry", {
+        id,
+        revision,
+        site,
+        username,
+        password: password ? password : null,
+        notes: notes.length ? notes : null,
+      });
+    } catch (e: unknown) {
+      // запись изменили в другом окне: показываем актуальную версию, правку не применяем
+      if (errorCode(e) !== "conflict") throw e;
+      await openEntry(id);
+      return;
+    }
     await reload();
-    const ent = await call<Entry>("get_entry", { id });
-    setSelected(ent);
-    setEditPwd("");
+    await openEntry(id);
   };
 
   const doSearch = async (e: React.FormEvent) => {
@@ -160,13 +331,14 @@ export default function App() {
 
   const generate = async (
     len = 20,
-    set?: (s: string) => void
+    set?: (s: string) => void,
+    target: { entryId?: number; site?: string } = {}
   ) => {
-    const s = await call<string>("generate_password", {
-      length: len,
-      useDigits: true,
-      useUpper: true,
-      useSymbols: true,
+    // профиль записи или сайта, если он есть, задаёт правила генерации
+    const { password: s } = await call<GeneratedPassword>("generate_password", {
+      options: { length: len },
+      entryId: target.entryId ?? null,
+      site: target.site || null,
     });
     set?.(s);
     try {
@@ -177,28 +349,100 @@ export default function App() {
     }
   };
 
+  const generatePassphrase = async (set?: (s: string) => void) => {
+    const { password: s } = await call<GeneratedPassword>("generate_passphrase", {
+      options: { words: 6, separator: "-", capitalize: "first", digit: true },
+    });
+    set?.(s);
+  };
+
 const exportBackup = async () => {
+  const passphrase = prompt("Backup password (needed to restore this file)");
+  if (!passphrase) return;
   const path = await save({ defaultPath: "backup.vault" });
   if (!path) return;
   // получаем зашифрованные байты с бэкапом
-  const bytes = await call<Uint8Array>("export_backup_bytes");
+  const bytes = await call<Uint8Array>("export_backup_bytes", { passphrase });
   await writeFile(path, bytes);
   alert("Exported");
 };
 
+// На новой установке пароль бэкапа становится мастер-паролем
 const importBackup = async () => {
   const path = await open({
     multiple: false,
     filters: [{ name: "Vault", extensions: ["vault"] }],
   });
   if (!path || Array.isArray(path)) return;
+  const passphrase = prompt("Backup password") ?? "";
   const bytes = await readFile(path);
   // invoke лучше кормить обычным массивом чисел
-  const count = await call<number>("import_backup_bytes", { data: Array.from(bytes) });
-  alert(`Imported ${count} entries`);
+  const r = await call<ImportSummary>("import_backup_bytes", {
+    data: Array.from(bytes),
+    passphrase,
+  });
+  alert(`Imported: ${r.inserted} new, ${r.updated} updated, ${r.skipped} skipped`);
   await reload();
 };
 
+// Незашифрованный экспорт: мастер-пароль спрашиваем заново
+const exportPlaintext = async () => {
+  const path = await save({
+    defaultPath: "vault-export.csv",
+    filters: [
+      { name: "CSV", extensions: ["csv"] },
+      { name: "JSON", extensions: ["json"] },
+    ],
+  });
+  if (!path) return;
+  const master = prompt("Re-enter master password to export UNENCRYPTED data");
+  if (!master) return;
+  const format = path.toLowerCase().endsWith(".json") ? "json" : "csv";
+  const count = await call<number>("export_plaintext", { path, master, format });
+  alert(`Exported ${count} entries in plain text. Delete the file when done.`);
+};
+
+const verifyBackup = async () => {
+  const path = await open({
+    multiple: false,
+    filters: [{ name: "Vault", extensions: ["vault"] }],
+  });
+  if (!path || Array.isArray(path)) return;
+  const passphrase = prompt("Backup password (leave empty for old backups)");
+  const r = await call<BackupReport>("verify_backup", {
+    path,
+    passphrase: passphrase ? passphrase : null,
+  });
+  const when = r.created_at ? fmt(r.created_at) : "unknown";
+  const status = r.problems.length ? `Problems:\n${r.problems.join("\n")}` : "OK";
+  alert(`Format v${r.format_version}, created ${when}, ${r.entry_count} entries\n${status}`);
+};
+
+const vaultAudit = async () => {
+  const r = await call<AuditReport>("vault_audit");
+  const name = (e: AuditEntryRef) => (e.username ? `${e.site} (${e.username})` : e.site);
+  const lines = [`${r.total} entries checked`];
+  r.reused.forEach((g) => lines.push(`Same password: ${g.map(name).join(", ")}`));
+  r.weak.forEach((w) => lines.push(`Weak (${STRENGTH_LABELS[w.score]}): ${name(w.entry)}`));
+  r.stale.forEach((s) => lines.push(`Not changed for ${s.age_days} days: ${name(s.entry)}`));
+  r.missing_username.forEach((e) => lines.push(`No username: ${name(e)}`));
+  r.duplicates.forEach((g) => lines.push(`Duplicates: ${g.map(name).join(", ")}`));
+  alert(lines.length > 1 ? lines.join("\n") : `${r.total} entries checked, no problems found`);
+};
+
+const breachCheck = async () => {
+  const dataset = await open({ multiple: false });
+  if (!dataset || Array.isArray(dataset)) return;
+  const r = await call<BreachReport>("breach_check", { dataset });
+  const name = (e: AuditEntryRef) => (e.username ? `${e.site} (${e.username})` : e.site);
+  const lines = r.breached.map((b) => `${name(b.entry)}: seen ${b.count} times`);
+  alert(
+    lines.length
+      ? `${lines.length} of ${r.checked} passwords found in breaches:\n${lines.join("\n")}`
+      : `${r.checked} passwords checked, none found in breaches`
+  );
+};
+
   const left = useMemo(() => {
     if (!unlocked) return null;
     return (
@@ -219,27 +463,82 @@ const importBackup = async () => {
           >
             Reset
           </button>
+          <label className="small">
+            <input
+              type="checkbox"
+              checked={weakestFirst}
+              onChange={(e) => {
+                setWeakestFirst(e.target.checked);
+                reload(e.target.checked);
+              }}
+            />
+            Weakest first
+          </label>
+          <label className="small">
+            <input
+              type="checkbox"
+              checked={searchNotes}
+              onChange={(e) => setSearchNotes(e.target.checked)}
+            />
+            In notes
+          </label>
+          <select
+            value={sortKey}
+            onChange={(e) => {
+              const key = e.target.value as SortKey;
+              setSortKey(key);
+              reload(weakestFirst, key);
+            }}
+          >
+            <option value="updated">Recently updated</option>
+            <option value="last_used">Recently used</option>
+            <option value="use_count">Most used</option>
+            <option value="created">Recently created</option>
+            <option value="site">Site A–Z</option>
+            <option value="username">Username A–Z</option>
+          </select>
         </form>
 
         <div className="list">
           {items.map((it) => (
             <div key={it.id} className="item">
               <div className="item-main" onClick={() => openEntry(it.id)}>
-                <b>{it.site}</b>
+                <b>
+                  {it.pin_order != null ? "📌 " : it.favorite ? "★ " : ""}
+                  {it.site}
+                </b>
                 <div className="muted">{it.username}</div>
-                <div className="muted small">{fmt(it.updated_at)}</div>
+                <div className="muted small">
+                  {fmt(it.updated_at)}
+                  {it.strength != null && ` · ${STRENGTH_LABELS[it.strength]}`}
+                </div>
               </div>
               <div className="item-actions">
+                <button onClick={() => toggleFavorite(it)}>
+                  {it.favorite ? "Unstar" : "Star"}
+                </button>
+                <button onClick={() => togglePin(it)}>
+                  {it.pin_order != null ? "Unpin" : "Pin"}
+                </button>
+                {it.pin_order != null && it.pin_order > 0 && (
+                  <button onClick={() => movePinUp(it)}>↑</button>
+                )}
+                <button onClick={() => copySecret(it.id, "password")}>Copy</button>
                 <button onClick={() => openEntry(it.id)}>Open</button>
                 <button onClick={() => handleDelete(it.id)}>Delete</button>
               </div>
             </div>
           ))}
           {items.length === 0 && <div className="muted">No items</div>}
+          {total > items.length && (
+            <div className="muted small">
+              Showing {items.length} of {total}
+            </div>
+          )}
         </div>
       </div>
     );
-  }, [unlocked, items, search]);
+  }, [unlocked, items, total, search, weakestFirst, searchNotes, sortKey]);
 
   const right = useMemo(() => {
     if (!unlocked) return null;
@@ -257,10 +556,28 @@ const importBackup = async () => {
               value={addPwd}
               onChange={(e) => setAddPwd(e.target.value)}
             />
-            <button type="button" onClick={() => generate(20, setAddPwd)}>
+            <button
+              type="button"
+              onClick={(e) => {
+                const site = e.currentTarget.form?.elements.namedItem("site");
+                generate(20, setAddPwd, {
+                  site: site instanceof HTMLInputElement ? site.value : undefined,
+                });
+              }}
+            >
               Generate
             </button>
+            <button type="button" onClick={() => generatePassphrase(setAddPwd)}>
+              Passphrase
+            </button>
           </div>
+          {addStrength && (
+            <div className="muted small">
+              Strength: {STRENGTH_LABELS[addStrength.score]} (
+              {Math.round(addStrength.entropy_bits)} bits)
+              {addStrength.warning && ` — ${addStrength.warning}`}
+            </div>
+          )}
           <textarea name="notes" placeholder="Notes (optional)" />
           <button type="submit">Add</button>
         </form>
@@ -268,7 +585,7 @@ const importBackup = async () => {
         {selected && (
           <>
             <h2>Edit entry</h2>
-            <form onSubmit={(e) => handleUpdate(e, selected.id)} className="col">
+            <form onSubmit={(e) => handleUpdate(e, selected.id, selected.revision)} className="col">
               <input name="site" defaultValue={selected.site} required />
               <input name="username" defaultValue={selected.username} required />
               <div className="row">
@@ -280,22 +597,48 @@ const importBackup = async () => {
                 />
                 <button
                   type="button"
-                  onClick={() => generate(20, setEditPwd)}
+                  onClick={() => generate(20, setEditPwd, { entryId: selected.id })}
                 >
                   Generate
                 </button>
               </div>
-              <textarea name="notes" defaultValue={selected.notes ?? ""} />
+              {selected.has_notes && revealed.notes === undefined ? (
+                // без textarea заметки уходят как null и остаются прежними
+                <button type="button" onClick={() => reveal(selected.id, "notes")}>
+                  Show notes to edit
+                </button>
+              ) : (
+                <textarea
+                  key={`${selected.id}:${selected.revision}:${revealed.notes !== undefined}`}
+                  name="notes"
+                  defaultValue={revealed.notes ?? ""}
+                />
+              )}
               <button type="submit">Save</button>
             </form>
 
-            <h3>Decrypted password</h3>
-            <code style={{ userSelect: "all" }}>{selected.password}</code>
+            <h3>Password</h3>
+            <div className="row">
+              {revealed.password === undefined ? (
+                <button onClick={() => reveal(selected.id, "password")}>Reveal</button>
+              ) : (
+                <>
+                  <code style={{ userSelect: "all" }}>{revealed.password}</code>
+                  <button onClick={() => setRevealed((r) => ({ ...r, password: undefined }))}>
+                    Hide
+                  </button>
+                </>
+              )}
+            </div>
+            <div className="row">
+              <button onClick={() => copySecret(selected.id, "password")}>Copy password</button>
+              <button onClick={() => copySecret(selected.id, "username")}>Copy username</button>
+            </div>
           </>
         )}
       </div>
     );
-  }, [unlocked, selected, addPwd, editPwd]);
+  }, [unlocked, selected, revealed, addPwd, editPwd, addStrength]);
 
   return (
     <main className="container">
@@ -313,10 +656,12 @@ const importBackup = async () => {
             />
             <button onClick={handleInit}>Init</button>
             <button onClick={handleUnlock}>Unlock</button>
+            <button onClick={importBackup}>Restore</button>
           </div>
           <p className="muted">
             New install? Click <b>Init</b>. Otherwise enter your master password
-            and click <b>Unlock</b>.
+            and click <b>Unlock</b>. To move to a new machine, click{" "}
+            <b>Restore</b> and pick a backup file.
           </p>
         </div>
       ) : (
@@ -324,6 +669,10 @@ const importBackup = async () => {
           <button onClick={handleLock}>Lock</button>
           <button onClick={exportBackup}>Export</button>
           <button onClick={importBackup}>Import</button>
+          <button onClick={verifyBackup}>Verify backup</button>
+          <button onClick={vaultAudit}>Health report</button>
+          <button onClick={breachCheck}>Check breaches</button>
+          <button onClick={exportPlaintext}>Export plaintext</button>
         </div>
       )}
 
@@ -0,0 +1,42 @@
+// Ошибки бэкенда приходят как { code, message, details } (см. VaultError в vault-core).
+// Тексты для пользователя — здесь, по коду; `message` — запасной английский текст.
+
+export type VaultError = {
+  code: string;
+  message: string;
+  details?: Record<string, unknown> | null;
+};
+
+const MESSAGES: Record<string, string> = {
+  locked: "Хранилище заблокировано — разблокируй мастер-паролем",
+  bad_password: "Неверный мастер-пароль или пароль бэкапа",
+  not_initialized: "Хранилище не инициализировано",
+  already_initialized: "Хранилище уже создано — войди мастер-паролем",
+  not_found: "Запись не найдена — возможно, её удалили в другом окне",
+  conflict: "Запись изменили в другом окне — открыта актуальная версия, внеси правку ещё раз",
+  crypto: "Не удалось расшифровать данные: файл повреждён или ключ не подходит",
+  database: "Ошибка базы данных: {message}",
+  unsupported_schema:
+    "База создана более новой версией приложения (схема {found}, поддерживается {supported}) — обнови приложение",
+  invalid_dataset:
+    "Файл базы утечек повреждён или не отсортирован — скачай вариант «ordered by hash» заново ({message})",
+  other: "{message}",
+};
+
+export function isVaultError(e: unknown): e is VaultError {
+  return typeof e === "object" && e !== null && typeof (e as any).code === "string";
+}
+
+export function errorCode(e: unknown): string | null {
+  return isVaultError(e) ? e.code : null;
+}
+
+/** Текст ошибки для пользователя: шаблон по коду с подстановкой `details` и `message`. */
+export function errorText(e: unknown): string {
+  if (!isVaultError(e)) {
+    return typeof e === "string" ? e : (e as any)?.message ?? JSON.stringify(e);
+  }
+  const template = MESSAGES[e.code] ?? "{message}";
+  const params: Record<string, unknown> = { ...(e.details ?? {}), message: e.message };
+  return template.replace(/\{(\w+)\}/g, (m, key) => (key in params ? String(params[key]) : m));
+}
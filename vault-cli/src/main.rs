@@ -0,0 +1,571 @@
+use std::{
+    io::BufRead,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use rand::{rngs::StdRng, SeedableRng};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+use serde_json::json;
+use vault_core::{
+    export::ExportFormat,
+    generator::{self, GeneratorMode, GeneratorOptions},
+    passphrase::{self, PassphraseOptions},
+    search::SearchOptions,
+    DataBase, Entry, EntryListItem, VaultError,
+};
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  success
+  1  other error
+  2  invalid command line
+  3  vault is not initialized (or there is no database file)
+  4  vault is already initialized
+  5  wrong master or backup password
+  6  vault is locked
+  7  decryption failed (corrupt data)
+  8  database error
+  9  entry not found
+ 10  several entries match, narrow down with --username or use the id
+ 11  the database was written by a newer version
+ 12  the entry was changed concurrently";
+
+/// Менеджер паролей в терминале; работает с той же базой, что и приложение.
+#[derive(Parser)]
+#[command(name = "vault", version, about, after_help = EXIT_CODES)]
+struct Cli {
+    /// Файл базы хранилища.
+    #[arg(long, env = "VAULT_DB", default_value = "app.db", global = true)]
+    db: PathBuf,
+    /// Вывод в JSON для скриптов.
+    #[arg(long, global = true)]
+    json: bool,
+    /// Читать мастер-пароль из первой строки stdin вместо запроса.
+    #[arg(long, global = true)]
+    master_stdin: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Создать хранилище с новым мастер-паролем.
+    Init,
+    /// Список записей без паролей.
+    List {
+        /// Нечёткий поиск по сайту и логину; лучшие совпадения первыми.
+        search: Option<String>,
+        /// Искать и в заметках.
+        #[arg(long, requires = "search")]
+        in_notes: bool,
+        /// Сначала самые слабые пароли (без поиска).
+        #[arg(long, conflicts_with = "search")]
+        weakest_first: bool,
+    },
+    /// Показать запись по id или сайту.
+    Get {
+        /// id записи или точное имя сайта.
+        target: String,
+        #[arg(long, short)]
+        username: Option<String>,
+        #[arg(long, short, value_enum, default_value_t = Field::All)]
+        field: Field,
+    },
+    /// Добавить запись; пароль запрашивается или генерируется (тогда он выводится).
+    Add {
+        site: String,
+        username: String,
+        #[arg(long)]
+        notes: Option<String>,
+        /// Сгенерировать пароль (с учётом профиля сайта) вместо запроса.
+        #[arg(long)]
+        generate: bool,
+    },
+    /// Сгенерировать пароль, PIN или парольную фразу.
+    Generate {
+        /// Длина (по умолчанию 20); для `--mode passphrase` вместо неё — `--words`.
+        #[arg(long, short)]
+        length: Option<usize>,
+        #[arg(long, value_enum, default_value_t = Mode::Random)]
+        mode: Mode,
+        #[arg(long)]
+        no_upper: bool,
+        #[arg(long)]
+        no_digits: bool,
+        #[arg(long)]
+        no_symbols: bool,
+        /// Без похожих символов (`l`, `1`, `O`, `0` …).
+        #[arg(long)]
+        avoid_ambiguous: bool,
+        /// Применить профиль генератора этого сайта.
+        #[arg(long)]
+        site: Option<String>,
+        /// Количество слов для `--mode passphrase` (по умолчанию 6).
+        #[arg(long)]
+        words: Option<usize>,
+    },
+    /// Экспорт: `vault` — зашифрованный бэкап, `json`/`csv` — открытым текстом.
+    Export {
+        path: PathBuf,
+        /// По умолчанию определяется по расширению файла.
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Field {
+    All,
+    Site,
+    Username,
+    Password,
+    Notes,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    Random,
+    Pin,
+    Pronounceable,
+    Passphrase,
+}
+
+#[derive(Clone, Copy, ValueEnum, PartialEq, Eq, Debug)]
+enum Format {
+    Vault,
+    Json,
+    Csv,
+}
+
+#[derive(Debug)]
+enum CliError {
+    Vault(VaultError),
+    /// Файла базы нет: хранилище не создано или путь указан неверно.
+    NoVault(PathBuf),
+    NotFound(String),
+    Ambiguous(Vec<EntryListItem>),
+    Input(String),
+}
+
+impl From<VaultError> for CliError {
+    fn from(e: VaultError) -> Self {
+        CliError::Vault(e)
+    }
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Vault(e) => match e {
+                VaultError::Other(_) | VaultError::InvalidDataset(_) => 1,
+                VaultError::NotInitialized => 3,
+                VaultError::AlreadyInitialized => 4,
+                VaultError::BadMasterPassword => 5,
+                VaultError::Locked => 6,
+                VaultError::NotFound { .. } => 9,
+                VaultError::Conflict { .. } => 12,
+                VaultError::Crypto => 7,
+                VaultError::Sqlx(_) => 8,
+                VaultError::UnsupportedSchema { .. } => 11,
+            },
+            CliError::NoVault(_) => 3,
+            CliError::Input(_) => 1,
+            CliError::NotFound(_) => 9,
+            CliError::Ambiguous(_) => 10,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            CliError::Vault(e) => e.to_string(),
+            CliError::NoVault(path) => {
+                format!(
+                    "no vault at {}; create one with `vault init`",
+                    path.display()
+                )
+            }
+            CliError::NotFound(what) => format!("no entry matches {what}"),
+            CliError::Ambiguous(items) => {
+                let list: Vec<String> = items
+                    .iter()
+                    .map(|i| format!("  {} {} {}", i.id, i.site, i.username))
+                    .collect();
+                format!("several entries match:\n{}", list.join("\n"))
+            }
+            CliError::Input(msg) => msg.clone(),
+        }
+    }
+
+    /// `{ code, message, details, exit_code }`, как у ошибок хранилища в приложении.
+    fn to_json(&self) -> serde_json::Value {
+        let (code, details) = match self {
+            CliError::Vault(e) => (e.code(), e.details()),
+            CliError::NoVault(path) => ("not_initialized", json!({ "path": path })),
+            CliError::NotFound(what) => ("not_found", json!({ "query": what })),
+            CliError::Ambiguous(items) => ("ambiguous", json!({ "matches": items })),
+            CliError::Input(_) => ("invalid_input", serde_json::Value::Null),
+        };
+        json!({
+            "code": code,
+            "message": self.message(),
+            "details": details,
+            "exit_code": self.exit_code(),
+        })
+    }
+}
+
+type CliResult<T> = Result<T, CliError>;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if cli.json {
+                eprintln!("{}", e.to_json());
+            } else {
+                eprintln!("vault: {}", e.message());
+            }
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+async fn run(cli: &Cli) -> CliResult<()> {
+    match &cli.command {
+        Command::Init => {
+            let db = DataBase::open(&cli.db).await?;
+            let master = if cli.master_stdin {
+                read_master(cli)?
+            } else {
+                prompt_new("Master password")?
+            };
+            db.init_master(master).await?;
+            print(cli, &json!({ "initialized": true }), || {
+                format!("Vault created in {}", cli.db.display())
+            });
+        }
+        Command::List {
+            search,
+            in_notes,
+            weakest_first,
+        } => {
+            let db = open(cli).await?;
+            unlock(cli, &db).await?;
+            let items = match search {
+                Some(query) => {
+                    let opts = SearchOptions {
+                        query: query.clone(),
+                        in_notes: *in_notes,
+                        limit: usize::MAX,
+                        ..Default::default()
+                    };
+                    let found = db.search_entries(&opts).await?;
+                    found.hits.into_iter().map(|h| h.entry).collect()
+                }
+                None => db.list_entries(None, *weakest_first).await?,
+            };
+            print(cli, &items, || {
+                items
+                    .iter()
+                    .map(|i| format!("{}\t{}\t{}", i.id, i.site, i.username))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        Command::Get {
+            target,
+            username,
+            field,
+        } => {
+            let db = open(cli).await?;
+            unlock(cli, &db).await?;
+            let id = resolve(&db, target, username.as_deref()).await?;
+            let entry = db.get_entry(id).await?;
+            print_entry(cli, &entry, *field);
+        }
+        Command::Add {
+            site,
+            username,
+            notes,
+            generate,
+        } => {
+            let db = open(cli).await?;
+            unlock(cli, &db).await?;
+            let password = if *generate {
+                let g = db
+                    .generate_password(&GeneratorOptions::default(), None, Some(site))
+                    .await?;
+                SecretString::new(g.password)
+            } else {
+                prompt_new("Entry password")?
+            };
+            let id = db
+                .add_entry(site, username, password.expose_secret(), notes.as_deref())
+                .await?;
+            // сгенерированный пароль больше нигде не увидеть, кроме как через `get`
+            if *generate {
+                let pw = password.expose_secret();
+                print(cli, &json!({ "id": id, "password": pw }), || {
+                    format!("Added entry {id}\nPassword: {pw}")
+                });
+            } else {
+                print(cli, &json!({ "id": id }), || format!("Added entry {id}"));
+            }
+        }
+        Command::Generate {
+            length,
+            mode,
+            no_upper,
+            no_digits,
+            no_symbols,
+            avoid_ambiguous,
+            site,
+            words,
+        } => {
+            let g = match mode {
+                Mode::Passphrase if length.is_some() => {
+                    return Err(CliError::Input(
+                        "--length does not apply to passphrases; use --words".into(),
+                    ));
+                }
+                Mode::Passphrase => {
+                    let opts = PassphraseOptions {
+                        words: words.unwrap_or(PassphraseOptions::default().words),
+                        ..Default::default()
+                    };
+                    let list = passphrase::builtin_wordlist();
+                    passphrase::generate(&opts, &list, &mut StdRng::from_os_rng())?
+                }
+                _ if words.is_some() => {
+                    return Err(CliError::Input(
+                        "--words only applies to --mode passphrase".into(),
+                    ));
+                }
+                _ => {
+                    let opts = GeneratorOptions {
+                        mode: match mode {
+                            Mode::Pin => GeneratorMode::Pin,
+                            Mode::Pronounceable => GeneratorMode::Pronounceable,
+                            _ => GeneratorMode::Random,
+                        },
+                        length: length.unwrap_or(GeneratorOptions::default().length),
+                        upper: !no_upper,
+                        digits: !no_digits,
+                        symbols: !no_symbols,
+                        avoid_ambiguous: *avoid_ambiguous,
+                        ..Default::default()
+                    };
+                    // без сайта профили не нужны, а значит, и хранилище
+                    match site {
+                        Some(site) => {
+                            let db = open(cli).await?;
+                            db.generate_password(&opts, None, Some(site)).await?
+                        }
+                        None => generator::generate(&opts, &mut StdRng::from_os_rng())?,
+                    }
+                }
+            };
+            print(cli, &g, || g.password.clone());
+        }
+        Command::Export { path, format } => {
+            let db = open(cli).await?;
+            let format = format.unwrap_or_else(|| format_for(path));
+            let master = read_master(cli)?;
+            db.unlock(master.clone()).await?;
+            let count = match format {
+                Format::Vault => {
+                    let passphrase = prompt_new("Backup password")?;
+                    db.export_encrypted_backup(path, &passphrase).await?;
+                    db.list_entries(None, false).await?.len()
+                }
+                Format::Json => {
+                    db.export_plaintext(path, &master, ExportFormat::Json)
+                        .await?
+                }
+                Format::Csv => {
+                    db.export_plaintext(path, &master, ExportFormat::Csv)
+                        .await?
+                }
+            };
+            print(cli, &json!({ "path": path, "entries": count }), || {
+                format!("Exported {count} entries to {}", path.display())
+            });
+        }
+    }
+    Ok(())
+}
+
+fn format_for(path: &Path) -> Format {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => Format::Csv,
+        Some(ext) if ext.eq_ignore_ascii_case("json") => Format::Json,
+        _ => Format::Vault,
+    }
+}
+
+/// id записи, либо единственная запись с таким сайтом (без учёта регистра).
+async fn resolve(db: &DataBase, target: &str, username: Option<&str>) -> CliResult<i64> {
+    if let Ok(id) = target.parse::<i64>() {
+        return Ok(id);
+    }
+    let items = db.list_entries(Some(target), false).await?;
+    pick(items, target, username)
+}
+
+fn pick(items: Vec<EntryListItem>, site: &str, username: Option<&str>) -> CliResult<i64> {
+    let mut matches: Vec<EntryListItem> = items
+        .into_iter()
+        .filter(|i| i.site.eq_ignore_ascii_case(site.trim()))
+        .filter(|i| username.is_none_or(|u| i.username == u))
+        .collect();
+    match matches.len() {
+        0 => Err(CliError::NotFound(match username {
+            Some(u) => format!("{site} / {u}"),
+            None => site.to_owned(),
+        })),
+        1 => Ok(matches.remove(0).id),
+        _ => Err(CliError::Ambiguous(matches)),
+    }
+}
+
+/// Существующее хранилище; создаёт его только `init`.
+async fn open(cli: &Cli) -> CliResult<DataBase> {
+    if !cli.db.is_file() {
+        return Err(CliError::NoVault(cli.db.clone()));
+    }
+    Ok(DataBase::open_existing(&cli.db).await?)
+}
+
+async fn unlock(cli: &Cli, db: &DataBase) -> CliResult<()> {
+    if !db.is_initialized().await? {
+        return Err(VaultError::NotInitialized.into());
+    }
+    db.unlock(read_master(cli)?).await?;
+    Ok(())
+}
+
+fn read_master(cli: &Cli) -> CliResult<SecretString> {
+    if cli.master_stdin {
+        let mut line = String::new();
+        std::io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(|e| CliError::Input(e.to_string()))?;
+        let master = line.trim_end_matches(['\r', '\n']).to_owned();
+        line.clear();
+        return Ok(SecretString::new(master));
+    }
+    prompt("Master password: ")
+}
+
+/// rpassword читает из терминала напрямую, поэтому запрос работает и при перенаправленном stdin.
+fn prompt(label: &str) -> CliResult<SecretString> {
+    rpassword::prompt_password(label)
+        .map(SecretString::new)
+        .map_err(|e| CliError::Input(format!("cannot read password: {e}")))
+}
+
+/// Новый пароль с подтверждением.
+fn prompt_new(what: &str) -> CliResult<SecretString> {
+    let first = prompt(&format!("{what}: "))?;
+    let second = prompt(&format!("Repeat {}: ", what.to_lowercase()))?;
+    if first.expose_secret() != second.expose_secret() {
+        return Err(CliError::Input("passwords do not match".into()));
+    }
+    if first.expose_secret().is_empty() {
+        return Err(CliError::Input("password must not be empty".into()));
+    }
+    Ok(first)
+}
+
+fn print<T: Serialize>(cli: &Cli, value: &T, text: impl FnOnce() -> String) {
+    if cli.json {
+        println!("{}", serde_json::to_string(value).unwrap());
+    } else {
+        let out = text();
+        if !out.is_empty() {
+            println!("{out}");
+        }
+    }
+}
+
+fn print_entry(cli: &Cli, e: &Entry, field: Field) {
+    let value = match field {
+        Field::All => {
+            return print(cli, e, || {
+                format!(
+                    "site:     {}\nusername: {}\npassword: {}\nnotes:    {}",
+                    e.site,
+                    e.username,
+                    e.password,
+                    e.notes.as_deref().unwrap_or("")
+                )
+            })
+        }
+        Field::Site => e.site.as_str(),
+        Field::Username => e.username.as_str(),
+        Field::Password => e.password.as_str(),
+        Field::Notes => e.notes.as_deref().unwrap_or(""),
+    };
+    print(cli, &json!({ "value": value }), || value.to_owned());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: i64, site: &str, username: &str) -> EntryListItem {
+        EntryListItem {
+            id,
+            site: site.into(),
+            username: username.into(),
+            strength: None,
+            created_at: 0,
+            updated_at: 0,
+            revision: 1,
+            last_used_at: None,
+            use_count: 0,
+            favorite: false,
+            pin_order: None,
+        }
+    }
+
+    #[test]
+    fn entries_are_picked_by_exact_site() {
+        let items = vec![
+            item(1, "github.com", "alice"),
+            item(2, "gist.github.com", "alice"),
+            item(3, "GitHub.com", "bob"),
+        ];
+        assert!(matches!(
+            pick(items.clone(), "github.com", None),
+            Err(CliError::Ambiguous(ref m)) if m.len() == 2
+        ));
+        assert_eq!(pick(items.clone(), "github.com", Some("bob")).unwrap(), 3);
+        assert_eq!(pick(items.clone(), "gist.github.com", None).unwrap(), 2);
+        let missing = pick(items, "gitlab.com", None).unwrap_err();
+        assert_eq!(missing.exit_code(), 9);
+        assert_eq!(missing.to_json()["code"], "not_found");
+    }
+
+    #[test]
+    fn vault_errors_map_to_exit_codes() {
+        let code = |e| CliError::Vault(e).exit_code();
+        assert_eq!(code(VaultError::NotInitialized), 3);
+        assert_eq!(code(VaultError::BadMasterPassword), 5);
+        assert_eq!(code(VaultError::Other("x".into())), 1);
+        assert_eq!(format_for(Path::new("out.CSV")), Format::Csv);
+        assert_eq!(format_for(Path::new("out.vault")), Format::Vault);
+    }
+
+    #[test]
+    fn cli_definition_is_valid() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+}
@@ -0,0 +1,80 @@
+//! Сквозная проверка бинарника `vault` на хранилище во временном каталоге.
+
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Output, Stdio},
+};
+
+const MASTER: &str = "master123";
+
+/// Запускает `vault --db <db> --master-stdin <args>` с мастер-паролем на stdin.
+fn vault(db: &Path, args: &[&str]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vault"))
+        .arg("--db")
+        .arg(db)
+        .arg("--master-stdin")
+        .args(args)
+        .env_remove("VAULT_DB")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // команды без хранилища stdin не читают и могут закрыть его раньше
+    let _ = writeln!(child.stdin.take().unwrap(), "{MASTER}");
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(out: &Output) -> String {
+    String::from_utf8(out.stdout.clone()).unwrap()
+}
+
+#[test]
+fn init_add_get_list() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = dir.path().join("vault.db");
+
+    // до init база не создаётся ни чтением, ни генератором
+    let out = vault(&db, &["list"]);
+    assert_eq!(out.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("vault init"));
+    let out = vault(&db, &["--json", "get", "example.com"]);
+    assert_eq!(out.status.code(), Some(3));
+    let err: serde_json::Value = serde_json::from_slice(&out.stderr).unwrap();
+    assert_eq!(err["code"], "not_initialized");
+    let out = vault(&db, &["generate", "--length", "24"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(stdout(&out).trim_end().chars().count(), 24);
+    assert!(vault(&db, &["generate", "--mode", "passphrase"])
+        .status
+        .success());
+    assert!(!db.exists());
+
+    let out = vault(&db, &["init"]);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    assert!(db.is_file());
+    assert_eq!(vault(&db, &["init"]).status.code(), Some(4));
+
+    let out = vault(
+        &db,
+        &["--json", "add", "example.com", "alice", "--generate"],
+    );
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let added: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let id = added["id"].as_i64().unwrap();
+    let password = added["password"].as_str().unwrap().to_owned();
+    assert!(!password.is_empty());
+
+    let out = vault(&db, &["get", "example.com", "--field", "password"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(stdout(&out), format!("{password}\n"));
+
+    let out = vault(&db, &["list"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(stdout(&out), format!("{id}\texample.com\talice\n"));
+
+    let out = vault(&db, &["get", "missing.example"]);
+    assert_eq!(out.status.code(), Some(9));
+    assert!(stdout(&out).is_empty());
+}
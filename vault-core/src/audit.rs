@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use super::strength;
+
+/// Параметры проверки хранилища.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditOptions {
+    /// Пароль старше стольких дней (по `updated_at`) считается устаревшим.
+    pub stale_after_days: u32,
+    /// Оценка стойкости ниже этой считается слабой (0–4).
+    pub weak_below: u8,
+}
+
+impl Default for AuditOptions {
+    fn default() -> Self {
+        Self {
+            stale_after_days: 365,
+            weak_below: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuditEntryRef {
+    pub id: i64,
+    pub site: String,
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WeakEntry {
+    pub entry: AuditEntryRef,
+    pub score: u8,
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StaleEntry {
+    pub entry: AuditEntryRef,
+    pub updated_at: i64,
+    pub age_days: i64,
+}
+
+/// Отчёт о состоянии хранилища. Сами пароли в отчёт не попадают.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditReport {
+    pub generated_at: i64,
+    pub total: usize,
+    /// Группы записей с одинаковым паролем.
+    pub reused: Vec<Vec<AuditEntryRef>>,
+    pub weak: Vec<WeakEntry>,
+    pub stale: Vec<StaleEntry>,
+    pub missing_username: Vec<AuditEntryRef>,
+    /// Группы записей с одинаковой парой сайт/логин (без учёта регистра).
+    pub duplicates: Vec<Vec<AuditEntryRef>>,
+}
+
+/// Расшифрованная запись для аудита; пароль затирается при drop.
+pub(crate) struct AuditInput {
+    pub(crate) entry: AuditEntryRef,
+    pub(crate) password: String,
+    pub(crate) updated_at: i64,
+}
+
+impl Drop for AuditInput {
+    fn drop(&mut self) {
+        self.password.zeroize();
+    }
+}
+
+pub(crate) fn build(items: &[AuditInput], opts: &AuditOptions, now: i64) -> AuditReport {
+    let stale_secs = opts.stale_after_days as i64 * 24 * 60 * 60;
+
+    let mut weak = Vec::new();
+    let mut stale = Vec::new();
+    let mut missing_username = Vec::new();
+    for it in items {
+        let e = &it.entry;
+        let r = strength::estimate(&it.password, &[&e.site, &e.username]);
+        if r.score < opts.weak_below {
+            weak.push(WeakEntry {
+                entry: e.clone(),
+                score: r.score,
+                warning: r.warning,
+            });
+        }
+        if now - it.updated_at > stale_secs {
+            stale.push(StaleEntry {
+                entry: e.clone(),
+                updated_at: it.updated_at,
+                age_days: (now - it.updated_at) / (24 * 60 * 60),
+            });
+        }
+        if e.username.trim().is_empty() {
+            missing_username.push(e.clone());
+        }
+    }
+    weak.sort_by_key(|w| w.score);
+    stale.sort_by_key(|s| s.updated_at);
+
+    // индексы группируются по паролю без копирования самих паролей
+    let mut by_password: Vec<usize> = (0..items.len()).collect();
+    by_password.sort_by(|&a, &b| items[a].password.cmp(&items[b].password));
+    let mut reused: Vec<Vec<AuditEntryRef>> = by_password
+        .chunk_by(|&a, &b| items[a].password == items[b].password)
+        .filter(|g| g.len() > 1)
+        .map(|g| g.iter().map(|&i| items[i].entry.clone()).collect())
+        .collect();
+    for g in reused.iter_mut() {
+        g.sort_by_key(|e| e.id);
+    }
+    reused.sort_by_key(|g| g[0].id);
+
+    let mut pairs: HashMap<(String, String), Vec<AuditEntryRef>> = HashMap::new();
+    for it in items {
+        let key = (
+            it.entry.site.trim().to_lowercase(),
+            it.entry.username.trim().to_lowercase(),
+        );
+        pairs.entry(key).or_default().push(it.entry.clone());
+    }
+    let mut duplicates: Vec<Vec<AuditEntryRef>> =
+        pairs.into_values().filter(|g| g.len() > 1).collect();
+    for g in duplicates.iter_mut() {
+        g.sort_by_key(|e| e.id);
+    }
+    duplicates.sort_by_key(|g| g[0].id);
+
+    AuditReport {
+        generated_at: now,
+        total: items.len(),
+        reused,
+        weak,
+        stale,
+        missing_username,
+        duplicates,
+    }
+}
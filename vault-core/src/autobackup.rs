@@ -0,0 +1,250 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+use super::db::{ResultT, VaultError};
+
+/// Настройки автоматических бэкапов. Хранятся в `backup_schedule` как JSON.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupSchedule {
+    /// Каталог, куда пишутся файлы `vault-YYYYMMDD-HHMMSS.vault`
+    /// (`vault-YYYYMMDD-HHMMSS-N.vault`, если за секунду их несколько).
+    pub dir: String,
+    /// Делать бэкап не реже, чем раз в столько секунд.
+    pub interval_secs: Option<u64>,
+    /// Делать бэкап после стольких изменений записей.
+    pub after_changes: Option<u64>,
+    /// Сколько последних дней хранить (по одному, самому свежему, файлу на день).
+    pub keep_daily: u32,
+    /// Сколько последних недель хранить (по одному файлу на ISO-неделю).
+    pub keep_weekly: u32,
+}
+
+impl Default for BackupSchedule {
+    fn default() -> Self {
+        Self {
+            dir: String::new(),
+            interval_secs: Some(24 * 60 * 60),
+            after_changes: None,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+/// Как прошли автоматические бэкапы. Ошибка хранится до следующего удачного бэкапа.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupStatus {
+    pub last_run_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<i64>,
+}
+
+/// Файл автоматического бэкапа в каталоге расписания.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StoredBackup {
+    pub file_name: String,
+    pub created_at: i64,
+    pub size: u64,
+}
+
+const PREFIX: &str = "vault-";
+const SUFFIX: &str = ".vault";
+
+pub(crate) fn file_name_for(ts: i64) -> String {
+    let dt = OffsetDateTime::from_unix_timestamp(ts).unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    format!(
+        "{PREFIX}{:04}{:02}{:02}-{:02}{:02}{:02}{SUFFIX}",
+        dt.year(),
+        dt.month() as u8,
+        dt.day(),
+        dt.hour(),
+        dt.minute(),
+        dt.second()
+    )
+}
+
+/// Имя для нового бэкапа в `dir`: второй и следующие бэкапы одной секунды
+/// получают номер `-1`, `-2`, … больше уже занятых. Номер не берётся из дыр,
+/// оставленных ротацией, иначе новый файл считался бы старше существующего.
+pub(crate) fn free_file_name(dir: &Path, ts: i64) -> ResultT<String> {
+    let base = file_name_for(ts);
+    let last = list_dir(dir)?
+        .iter()
+        .filter_map(|b| parse_file_name(&b.file_name))
+        .filter(|&(created_at, _)| created_at == ts)
+        .map(|(_, seq)| seq)
+        .max();
+    Ok(match last {
+        None => base,
+        Some(seq) => {
+            let stem = base.strip_suffix(SUFFIX).unwrap_or(&base);
+            format!("{stem}-{}{SUFFIX}", seq + 1)
+        }
+    })
+}
+
+/// Обратная к `file_name_for` и `free_file_name`: время и номер внутри секунды
+/// (0 — без номера). Чужие файлы в каталоге дают `None`.
+pub(crate) fn parse_file_name(name: &str) -> Option<(i64, u32)> {
+    let stamp = name.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?;
+    let (d, rest) = stamp.split_once('-')?;
+    let (t, seq) = match rest.split_once('-') {
+        // номер без ведущих нулей, чтобы у каждого бэкапа было ровно одно имя
+        Some((t, n)) if !n.starts_with('0') && n.bytes().all(|b| b.is_ascii_digit()) => {
+            (t, n.parse::<u32>().ok()?)
+        }
+        Some(_) => return None,
+        None => (rest, 0),
+    };
+    if d.len() != 8 || t.len() != 6 || !d.bytes().chain(t.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let num = |s: &str| s.parse::<u32>().ok();
+    let date = Date::from_calendar_date(
+        num(&d[..4])? as i32,
+        Month::try_from(num(&d[4..6])? as u8).ok()?,
+        num(&d[6..])? as u8,
+    )
+    .ok()?;
+    let time = Time::from_hms(
+        num(&t[..2])? as u8,
+        num(&t[2..4])? as u8,
+        num(&t[4..])? as u8,
+    )
+    .ok()?;
+    let ts = PrimitiveDateTime::new(date, time).assume_utc().unix_timestamp();
+    Some((ts, seq))
+}
+
+/// Список бэкапов в каталоге, от новых к старым.
+pub(crate) fn list_dir(dir: &Path) -> ResultT<Vec<StoredBackup>> {
+    let rd = match std::fs::read_dir(dir) {
+        Ok(rd) => rd,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(VaultError::Other(e.to_string())),
+    };
+    let mut out = Vec::new();
+    for ent in rd {
+        let ent = ent.map_err(|e| VaultError::Other(e.to_string()))?;
+        let name = ent.file_name().to_string_lossy().into_owned();
+        let Some((created_at, seq)) = parse_file_name(&name) else {
+            continue;
+        };
+        let size = ent.metadata().map(|m| m.len()).unwrap_or(0);
+        out.push((
+            seq,
+            StoredBackup {
+                file_name: name,
+                created_at,
+                size,
+            },
+        ));
+    }
+    out.sort_by_key(|(seq, b)| std::cmp::Reverse((b.created_at, *seq)));
+    Ok(out.into_iter().map(|(_, b)| b).collect())
+}
+
+/// Путь к файлу бэкапа внутри каталога; имена не по шаблону отклоняются,
+/// чтобы команды restore/verify не выходили за пределы каталога.
+pub(crate) fn resolve(dir: &Path, file_name: &str) -> ResultT<PathBuf> {
+    if parse_file_name(file_name).is_none() {
+        return Err(VaultError::Other(format!("not a scheduled backup: {file_name}")));
+    }
+    Ok(dir.join(file_name))
+}
+
+/// Индексы бэкапов (отсортированных от новых к старым), которые политика ротации
+/// не сохраняет: остаётся самый свежий файл каждого из `keep_daily` последних дней
+/// и каждой из `keep_weekly` последних ISO-недель. Самый новый бэкап не удаляется никогда.
+pub(crate) fn rotation_victims(stamps: &[i64], keep_daily: u32, keep_weekly: u32) -> Vec<usize> {
+    let mut keep = HashSet::new();
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    for (i, &ts) in stamps.iter().enumerate() {
+        let date = OffsetDateTime::from_unix_timestamp(ts)
+            .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+            .date();
+        if days.len() < keep_daily as usize && days.insert(date) {
+            keep.insert(i);
+        }
+        let (y, w, _) = date.to_iso_week_date();
+        if weeks.len() < keep_weekly as usize && weeks.insert((y, w)) {
+            keep.insert(i);
+        }
+    }
+    (1..stamps.len()).filter(|i| !keep.contains(i)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    #[test]
+    fn file_names_round_trip() {
+        let ts = 1_700_000_123;
+        let name = file_name_for(ts);
+        assert_eq!(name, "vault-20231114-221523.vault");
+        assert_eq!(parse_file_name(&name), Some((ts, 0)));
+        assert_eq!(parse_file_name("vault-20231114-221523-2.vault"), Some((ts, 2)));
+        assert_eq!(parse_file_name("vault-20231114-221523-0.vault"), None);
+        assert_eq!(parse_file_name("vault-20231114-221523-02.vault"), None);
+        assert_eq!(parse_file_name("vault-20231114-221523-.vault"), None);
+        assert_eq!(parse_file_name("vault-2023111-221523.vault"), None);
+        assert_eq!(parse_file_name("../vault-20231114-221523.vault"), None);
+        assert!(resolve(Path::new("/tmp"), "notes.txt").is_err());
+    }
+
+    #[test]
+    fn same_second_backups_get_increasing_numbers() {
+        let dir = tempfile::tempdir().unwrap();
+        let ts = 1_700_000_123;
+        let touch = |name: &str| std::fs::write(dir.path().join(name), b"").unwrap();
+
+        assert_eq!(free_file_name(dir.path(), ts).unwrap(), "vault-20231114-221523.vault");
+        touch("vault-20231114-221523.vault");
+        assert_eq!(free_file_name(dir.path(), ts).unwrap(), "vault-20231114-221523-1.vault");
+        touch("vault-20231114-221523-2.vault");
+        // дыра после ротации не занимается: новый файл должен остаться самым новым
+        std::fs::remove_file(dir.path().join("vault-20231114-221523.vault")).unwrap();
+        assert_eq!(free_file_name(dir.path(), ts).unwrap(), "vault-20231114-221523-3.vault");
+
+        touch("vault-20231114-221523-3.vault");
+        let names: Vec<_> = list_dir(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|b| b.file_name)
+            .collect();
+        assert_eq!(names, ["vault-20231114-221523-3.vault", "vault-20231114-221523-2.vault"]);
+    }
+
+    #[test]
+    fn rotation_keeps_daily_and_weekly() {
+        // с понедельника 2024-01-01 12:00 UTC по среду 2024-01-31, два бэкапа в день
+        let start = 1_704_110_400;
+        let mut stamps: Vec<i64> = (0..60).map(|i| start + i * DAY / 2).collect();
+        stamps.reverse();
+
+        let victims = rotation_victims(&stamps, 7, 4);
+        let kept: Vec<i64> = (0..stamps.len())
+            .filter(|i| !victims.contains(i))
+            .map(|i| stamps[i])
+            .collect();
+
+        assert_eq!(kept[0], stamps[0]);
+        // 7 дней (25–31 января) покрывают две последние недели,
+        // ещё две недели дают воскресенья 14 и 21 января
+        assert_eq!(kept.len(), 9);
+        let mut days: Vec<_> = kept.iter().map(|t| t / DAY).collect();
+        days.dedup();
+        assert_eq!(days.len(), kept.len());
+
+        assert_eq!(rotation_victims(&stamps, 0, 0).len(), stamps.len() - 1);
+    }
+}
@@ -0,0 +1,250 @@
+use getrandom::getrandom;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use super::crypto::{argon2_from_params, decrypt_aad, encrypt_aad, KdfParams};
+use super::db::{ResultT, VaultError};
+
+/// Самодостаточный бэкап:
+/// MAGIC(8) | version(1) | salt(16) | mem_cost(4) | iterations(4) | parallelism(4) | nonce||ciphertext
+///
+/// Ключ выводится из пароля бэкапа через Argon2id с солью и параметрами из заголовка,
+/// поэтому файл расшифровывается на любой установке, независимо от мастер-пароля хранилища.
+/// Весь заголовок аутентифицируется как AAD.
+pub(crate) const MAGIC: &[u8; 8] = b"PVAULTBK";
+pub(crate) const FORMAT_VERSION: u8 = 1;
+
+pub(crate) const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + 4 * 3;
+
+/// Потолок параметров Argon2 из заголовка. Заголовок проверяется только после
+/// вывода ключа, поэтому без потолка подделанный файл заставил бы выделить
+/// терабайты памяти или считать ключ бесконечно.
+const MAX_MEM_COST_KIB: u32 = 1 << 20;
+const MAX_ITERATIONS: u32 = 10;
+const MAX_PARALLELISM: u32 = 16;
+
+/// Запись бэкапа в открытом виде; пароль и заметки затираются при drop.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BackupEntry {
+    pub(crate) site: String,
+    pub(crate) username: String,
+    pub(crate) password: String,
+    pub(crate) notes: Option<String>,
+    #[serde(default)]
+    pub(crate) created_at: i64,
+    #[serde(default)]
+    pub(crate) updated_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rotate_days: Option<u32>,
+}
+
+impl Drop for BackupEntry {
+    fn drop(&mut self) {
+        self.password.zeroize();
+        self.notes.zeroize();
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BackupPayload {
+    pub(crate) created_at: i64,
+    pub(crate) entries: Vec<BackupEntry>,
+}
+
+pub(crate) fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn header(salt: &[u8; SALT_LEN], params: &KdfParams) -> Vec<u8> {
+    let mut h = Vec::with_capacity(HEADER_LEN);
+    h.extend_from_slice(MAGIC);
+    h.push(FORMAT_VERSION);
+    h.extend_from_slice(salt);
+    h.extend_from_slice(&params.mem_cost_kib.to_le_bytes());
+    h.extend_from_slice(&params.iterations.to_le_bytes());
+    h.extend_from_slice(&params.parallelism.to_le_bytes());
+    h
+}
+
+pub(crate) fn derive_key(
+    passphrase: &SecretString,
+    salt: &[u8],
+    params: &KdfParams,
+) -> ResultT<[u8; 32]> {
+    let mut key = [0u8; 32];
+    argon2_from_params(params)
+        .hash_password_into(passphrase.expose_secret().as_bytes(), salt, &mut key)
+        .map_err(|_| VaultError::Crypto)?;
+    Ok(key)
+}
+
+/// Шифрует произвольный JSON-пейлоад паролем бэкапа со свежей солью.
+pub(crate) fn seal(passphrase: &SecretString, plaintext: &[u8]) -> ResultT<Vec<u8>> {
+    if passphrase.expose_secret().is_empty() {
+        return Err(VaultError::Other("backup passphrase must not be empty".into()));
+    }
+    let salt = new_salt()?;
+    let params = KdfParams::default();
+
+    let mut key = derive_key(passphrase, &salt, &params)?;
+    let out = seal_with_key(&key, &salt, &params, plaintext);
+    key.zeroize();
+    out
+}
+
+/// То же, но ключом, заранее выведенным из пароля бэкапа с этими солью и параметрами.
+pub(crate) fn seal_with_key(
+    key: &[u8; 32],
+    salt: &[u8; SALT_LEN],
+    params: &KdfParams,
+    plaintext: &[u8],
+) -> ResultT<Vec<u8>> {
+    let mut out = header(salt, params);
+    let sealed = encrypt_aad(key, plaintext, &out)?;
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+pub(crate) fn new_salt() -> ResultT<[u8; SALT_LEN]> {
+    let mut salt = [0u8; SALT_LEN];
+    getrandom(&mut salt).map_err(|_| VaultError::Crypto)?;
+    Ok(salt)
+}
+
+/// Проверяет заголовок и расшифровывает бэкап. Неверный пароль даёт `BadMasterPassword`.
+pub(crate) fn open(passphrase: &SecretString, data: &[u8]) -> ResultT<Vec<u8>> {
+    let (salt, params) = parse_header(data)?;
+    let mut key = derive_key(passphrase, &salt, &params)?;
+    let plain = open_with_key(&key, data);
+    key.zeroize();
+    plain
+}
+
+/// Расшифровывает бэкап заранее выведенным ключом.
+pub(crate) fn open_with_key(key: &[u8; 32], data: &[u8]) -> ResultT<Vec<u8>> {
+    parse_header(data)?;
+    let (hdr, body) = data.split_at(HEADER_LEN);
+    decrypt_aad(key, body, hdr)
+}
+
+fn parse_header(data: &[u8]) -> ResultT<([u8; SALT_LEN], KdfParams)> {
+    if !is_sealed(data) || data.len() < HEADER_LEN {
+        return Err(VaultError::Other("not a vault backup file".into()));
+    }
+    let version = data[MAGIC.len()];
+    if version != FORMAT_VERSION {
+        return Err(VaultError::Other(format!(
+            "unsupported backup format version {version}"
+        )));
+    }
+    let hdr = &data[..HEADER_LEN];
+    let mut salt = [0u8; SALT_LEN];
+    salt.copy_from_slice(&hdr[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN]);
+    let mut rest = hdr[MAGIC.len() + 1 + SALT_LEN..]
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]));
+    let params = KdfParams {
+        mem_cost_kib: rest.next().unwrap_or_default(),
+        iterations: rest.next().unwrap_or_default(),
+        parallelism: rest.next().unwrap_or_default(),
+    };
+    if argon2::Params::new(params.mem_cost_kib, params.iterations, params.parallelism, None)
+        .is_err()
+    {
+        return Err(VaultError::Other("corrupt backup header".into()));
+    }
+    if params.mem_cost_kib > MAX_MEM_COST_KIB
+        || params.iterations > MAX_ITERATIONS
+        || params.parallelism > MAX_PARALLELISM
+    {
+        return Err(VaultError::Other(
+            "backup key derivation parameters exceed the supported limits".into(),
+        ));
+    }
+    Ok((salt, params))
+}
+
+/// Результат проверки бэкапа без импорта.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupReport {
+    /// Версия формата; 0 — старый бэкап, зашифрованный ключом хранилища.
+    pub format_version: u8,
+    /// Время создания из пейлоада (у старых бэкапов его нет).
+    pub created_at: Option<i64>,
+    pub entry_count: usize,
+    /// Структурные проблемы; пустой список означает, что бэкап цел.
+    pub problems: Vec<String>,
+}
+
+impl BackupReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Разбирает расшифрованный пейлоад и собирает структурные проблемы.
+pub(crate) fn inspect(format_version: u8, plain: &[u8], now: i64) -> BackupReport {
+    let mut report = BackupReport {
+        format_version,
+        created_at: None,
+        entry_count: 0,
+        problems: Vec::new(),
+    };
+    let items = match serde_json::from_slice::<serde_json::Value>(plain) {
+        Ok(serde_json::Value::Array(items)) => {
+            if format_version != 0 {
+                report.problems.push("payload has no metadata header".into());
+            }
+            items
+        }
+        Ok(serde_json::Value::Object(mut obj)) => {
+            report.created_at = obj.get("created_at").and_then(|v| v.as_i64());
+            if report.created_at.is_none() {
+                report.problems.push("payload has no created_at".into());
+            }
+            match obj.remove("entries") {
+                Some(serde_json::Value::Array(items)) => items,
+                _ => {
+                    report.problems.push("payload has no entries list".into());
+                    return report;
+                }
+            }
+        }
+        Ok(_) => {
+            report.problems.push("payload is neither a list nor an object".into());
+            return report;
+        }
+        Err(e) => {
+            report.problems.push(format!("payload is not valid JSON: {e}"));
+            return report;
+        }
+    };
+    report.entry_count = items.len();
+
+    for (i, raw) in items.into_iter().enumerate() {
+        let it: BackupEntry = match serde_json::from_value(raw) {
+            Ok(it) => it,
+            Err(e) => {
+                report.problems.push(format!("item {i}: {e}"));
+                continue;
+            }
+        };
+        if it.site.trim().is_empty() {
+            report.problems.push(format!("item {i}: empty site"));
+        }
+        if it.created_at > now || it.updated_at > now {
+            report.problems.push(format!("item {i}: timestamp in the future"));
+        }
+        if it.updated_at != 0 && it.updated_at < it.created_at {
+            report.problems.push(format!("item {i}: updated before it was created"));
+        }
+    }
+    if let Some(created_at) = report.created_at {
+        if created_at > now {
+            report.problems.push("backup creation time is in the future".into());
+        }
+    }
+    report
+}
@@ -0,0 +1,442 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use zeroize::Zeroize;
+
+use super::audit::{AuditEntryRef, AuditInput};
+use super::db::{ResultT, VaultError};
+
+/// Локальная копия базы Have I Been Pwned (SHA-1). Поддерживаются:
+/// - текстовый файл `HASH:COUNT`, отсортированный по хэшу (pwned-passwords-sha1-ordered-by-hash);
+/// - каталог файлов диапазонов `XXXXX.txt` со строками `SUFFIX:COUNT`, как у официального загрузчика;
+/// - компактный бинарный индекс, собранный `build_index` из текстового файла.
+///
+/// Хэши паролей хранилища считаются только в памяти и на диск не пишутся.
+pub(crate) enum Dataset {
+    Text { file: BufReader<File>, len: u64 },
+    Ranges(PathBuf),
+    Index { file: File, count: u64 },
+}
+
+/// Бинарный индекс: MAGIC(8) | version(1) | count(8, LE) | count × (sha1(20) | breach_count(4, LE))
+const INDEX_MAGIC: &[u8; 8] = b"PVHIBPIX";
+const INDEX_VERSION: u8 = 1;
+const INDEX_HEADER_LEN: u64 = 8 + 1 + 8;
+const RECORD_LEN: u64 = 20 + 4;
+
+const PREFIX_HEX: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BreachedEntry {
+    pub entry: AuditEntryRef,
+    /// Сколько раз пароль встречается в утечках.
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BreachReport {
+    pub checked: usize,
+    /// Записи с паролями из утечек, самые частые — первыми.
+    pub breached: Vec<BreachedEntry>,
+}
+
+fn io_err(e: std::io::Error) -> VaultError {
+    VaultError::Other(e.to_string())
+}
+
+fn bad_line(line: &str) -> VaultError {
+    VaultError::Other(format!("malformed HIBP line: {}", line.trim_end()))
+}
+
+fn invalid(reason: &str) -> VaultError {
+    VaultError::InvalidDataset(format!("{reason} (use the ordered-by-hash download)"))
+}
+
+impl Dataset {
+    /// Формат определяется по пути: каталог — диапазоны, файл с MAGIC — индекс, иначе текст.
+    pub(crate) fn open(path: &Path) -> ResultT<Self> {
+        if path.is_dir() {
+            return Ok(Dataset::Ranges(path.to_path_buf()));
+        }
+        let mut file = File::open(path).map_err(io_err)?;
+        let len = file.metadata().map_err(io_err)?.len();
+
+        let mut head = [0u8; INDEX_HEADER_LEN as usize];
+        if len >= INDEX_HEADER_LEN {
+            file.read_exact(&mut head).map_err(io_err)?;
+        }
+        if head.starts_with(INDEX_MAGIC) {
+            if head[8] != INDEX_VERSION {
+                return Err(VaultError::Other(format!(
+                    "unsupported HIBP index version {}",
+                    head[8]
+                )));
+            }
+            let mut n = [0u8; 8];
+            n.copy_from_slice(&head[9..]);
+            let count = u64::from_le_bytes(n);
+            let expected = count
+                .checked_mul(RECORD_LEN)
+                .and_then(|n| n.checked_add(INDEX_HEADER_LEN));
+            if expected != Some(len) {
+                return Err(VaultError::Other("truncated HIBP index".into()));
+            }
+            return Ok(Dataset::Index { file, count });
+        }
+        Ok(Dataset::Text {
+            file: BufReader::new(file),
+            len,
+        })
+    }
+
+    /// Сколько раз хэш встречается в базе; `None` — не встречается.
+    pub(crate) fn lookup(&mut self, hash: &[u8; 20]) -> ResultT<Option<u64>> {
+        match self {
+            Dataset::Text { file, len } => lookup_text(file, *len, hash),
+            Dataset::Ranges(dir) => lookup_range(dir, hash),
+            Dataset::Index { file, count } => lookup_index(file, *count, hash),
+        }
+    }
+}
+
+/// Проверяет пароли по базе; пароли и их хэши живут только в памяти.
+pub(crate) fn check(dataset: &mut Dataset, items: &[AuditInput]) -> ResultT<BreachReport> {
+    let mut breached = Vec::new();
+    for it in items {
+        let mut hash: [u8; 20] = Sha1::digest(it.password.as_bytes()).into();
+        let found = dataset.lookup(&hash);
+        hash.zeroize();
+        if let Some(count) = found? {
+            breached.push(BreachedEntry {
+                entry: it.entry.clone(),
+                count,
+            });
+        }
+    }
+    breached.sort_by_key(|b| std::cmp::Reverse(b.count));
+    Ok(BreachReport {
+        checked: items.len(),
+        breached,
+    })
+}
+
+/// Собирает бинарный индекс из отсортированного текстового файла. Возвращает число записей.
+pub fn build_index(src: &Path, dst: &Path) -> ResultT<u64> {
+    let input = BufReader::new(File::open(src).map_err(io_err)?);
+    let mut out = BufWriter::new(File::create(dst).map_err(io_err)?);
+    out.write_all(&[0u8; INDEX_HEADER_LEN as usize]).map_err(io_err)?;
+
+    let mut count = 0u64;
+    let mut prev: Option<[u8; 20]> = None;
+    for line in input.lines() {
+        let line = line.map_err(io_err)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (hash, n) = parse_line(&line, 40)?;
+        let mut full = [0u8; 20];
+        full.copy_from_slice(&hash);
+        if prev.is_some_and(|p| p >= full) {
+            return Err(VaultError::Other(
+                "HIBP file is not sorted by hash (use the ordered-by-hash download)".into(),
+            ));
+        }
+        prev = Some(full);
+        out.write_all(&full).map_err(io_err)?;
+        out.write_all(&(n.min(u32::MAX as u64) as u32).to_le_bytes())
+            .map_err(io_err)?;
+        count += 1;
+    }
+
+    let mut file = out.into_inner().map_err(|e| io_err(e.into_error()))?;
+    file.seek(SeekFrom::Start(0)).map_err(io_err)?;
+    file.write_all(INDEX_MAGIC).map_err(io_err)?;
+    file.write_all(&[INDEX_VERSION]).map_err(io_err)?;
+    file.write_all(&count.to_le_bytes()).map_err(io_err)?;
+    file.sync_all().map_err(io_err)?;
+    Ok(count)
+}
+
+/// Разбирает `HEX:COUNT` с хэшем (или его суффиксом) из `hex_len` символов.
+fn parse_line(line: &str, hex_len: usize) -> ResultT<(Vec<u8>, u64)> {
+    let line = line.trim_end();
+    let (hex, count) = line.split_once(':').ok_or_else(|| bad_line(line))?;
+    if hex.len() != hex_len {
+        return Err(bad_line(line));
+    }
+    let nibbles = hex
+        .bytes()
+        .map(|b| (b as char).to_digit(16).map(|d| d as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| bad_line(line))?;
+    let count = count.trim().parse::<u64>().map_err(|_| bad_line(line))?;
+    Ok((pack(&nibbles), count))
+}
+
+/// Полубайты в байты; нечётный хвост остаётся старшим полубайтом.
+fn pack(nibbles: &[u8]) -> Vec<u8> {
+    nibbles
+        .chunks(2)
+        .map(|c| c[0] << 4 | c.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+fn hash_nibbles(hash: &[u8; 20]) -> [u8; 40] {
+    let mut out = [0u8; 40];
+    for (i, b) in hash.iter().enumerate() {
+        out[2 * i] = b >> 4;
+        out[2 * i + 1] = b & 0x0f;
+    }
+    out
+}
+
+/// Первая строка, начинающаяся не раньше `off`: (начало, конец, строка).
+fn line_at(file: &mut BufReader<File>, off: u64) -> ResultT<Option<(u64, u64, String)>> {
+    let mut start = off;
+    if off > 0 {
+        file.seek(SeekFrom::Start(off - 1)).map_err(io_err)?;
+        let mut skipped = Vec::new();
+        start = off - 1 + file.read_until(b'\n', &mut skipped).map_err(io_err)? as u64;
+    } else {
+        file.seek(SeekFrom::Start(0)).map_err(io_err)?;
+    }
+    let mut line = String::new();
+    let n = file.read_line(&mut line).map_err(io_err)?;
+    if n == 0 {
+        return Ok(None);
+    }
+    Ok(Some((start, start + n as u64, line)))
+}
+
+/// Двоичный поиск по байтовым смещениям: файл целиком не читается, поэтому порядок
+/// проверяется на пути поиска. Каждая прочитанная строка должна лежать строго между
+/// уже пройденными границами окна и быть в верхнем регистре, иначе «не найдено»
+/// могло бы оказаться ложным — такой файл отклоняется целиком.
+fn lookup_text(file: &mut BufReader<File>, len: u64, hash: &[u8; 20]) -> ResultT<Option<u64>> {
+    let (mut lo, mut hi) = (0u64, len);
+    let (mut below, mut above): (Option<Vec<u8>>, Option<Vec<u8>>) = (None, None);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let Some((start, end, line)) = line_at(file, mid)? else {
+            hi = mid;
+            continue;
+        };
+        if start >= hi {
+            hi = mid;
+            continue;
+        }
+        if line.trim().is_empty() {
+            lo = end;
+            continue;
+        }
+        let hex = line.split(':').next().unwrap_or_default();
+        if hex.bytes().any(|b| b.is_ascii_lowercase()) {
+            return Err(invalid("HIBP hashes must be upper-case hex"));
+        }
+        let (found, count) = parse_line(&line, 40)
+            .map_err(|_| invalid(&format!("malformed line {:?}", line.trim_end())))?;
+        if below.as_ref().is_some_and(|b| *b >= found) || above.as_ref().is_some_and(|a| *a <= found)
+        {
+            return Err(invalid("HIBP file is not sorted by hash"));
+        }
+        match found.as_slice().cmp(hash.as_slice()) {
+            std::cmp::Ordering::Equal => return Ok(Some(count)),
+            std::cmp::Ordering::Less => {
+                lo = end;
+                below = Some(found);
+            }
+            std::cmp::Ordering::Greater => {
+                hi = mid;
+                above = Some(found);
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn lookup_range(dir: &Path, hash: &[u8; 20]) -> ResultT<Option<u64>> {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    let nibbles = hash_nibbles(hash);
+    let prefix: String = nibbles[..PREFIX_HEX]
+        .iter()
+        .map(|&n| HEX[n as usize] as char)
+        .collect();
+
+    let path = [format!("{prefix}.txt"), prefix.clone()]
+        .into_iter()
+        .map(|name| dir.join(name))
+        .find(|p| p.is_file())
+        .ok_or_else(|| VaultError::Other(format!("HIBP range file {prefix} is missing")))?;
+
+    let suffix = &nibbles[PREFIX_HEX..];
+    for line in BufReader::new(File::open(path).map_err(io_err)?).lines() {
+        let line = line.map_err(io_err)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (found, count) = parse_line(&line, 40 - PREFIX_HEX)?;
+        if found == pack(suffix) {
+            return Ok(Some(count));
+        }
+    }
+    Ok(None)
+}
+
+fn lookup_index(file: &mut File, count: u64, hash: &[u8; 20]) -> ResultT<Option<u64>> {
+    let (mut lo, mut hi) = (0u64, count);
+    let mut rec = [0u8; RECORD_LEN as usize];
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        file.seek(SeekFrom::Start(INDEX_HEADER_LEN + mid * RECORD_LEN))
+            .map_err(io_err)?;
+        file.read_exact(&mut rec).map_err(io_err)?;
+        match rec[..20].cmp(hash.as_slice()) {
+            std::cmp::Ordering::Equal => {
+                let n = u32::from_le_bytes([rec[20], rec[21], rec[22], rec[23]]);
+                return Ok(Some(n as u64));
+            }
+            std::cmp::Ordering::Less => lo = mid + 1,
+            std::cmp::Ordering::Greater => hi = mid,
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn sha1_hex(s: &str) -> String {
+        Sha1::digest(s.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect()
+    }
+
+    fn input(id: i64, password: &str) -> AuditInput {
+        AuditInput {
+            entry: AuditEntryRef {
+                id,
+                site: format!("site{id}"),
+                username: String::new(),
+            },
+            password: password.into(),
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn text_ranges_and_index_agree() {
+        let dir = tempdir().unwrap();
+        let mut pwned: Vec<(String, u64)> = (0..200)
+            .map(|i| (sha1_hex(&format!("pwned{i}")), i + 1))
+            .collect();
+        pwned.push((sha1_hex("password"), 9_000_000));
+        pwned.sort();
+
+        let text = dir.path().join("pwned.txt");
+        let body: String = pwned.iter().map(|(h, n)| format!("{h}:{n}\r\n")).collect();
+        std::fs::write(&text, body).unwrap();
+
+        let ranges = dir.path().join("ranges");
+        std::fs::create_dir(&ranges).unwrap();
+        for (h, n) in &pwned {
+            let path = ranges.join(format!("{}.txt", &h[..5]));
+            let mut f = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .unwrap();
+            writeln!(f, "{}:{n}", &h[5..]).unwrap();
+        }
+        // у официального загрузчика есть все диапазоны, в том числе пустые для наших паролей
+        for p in ["k9#Vq!2xLm@8Zr", ""] {
+            let path = ranges.join(format!("{}.txt", &sha1_hex(p)[..5]));
+            std::fs::OpenOptions::new().create(true).append(true).open(path).unwrap();
+        }
+
+        let index = dir.path().join("pwned.idx");
+        assert_eq!(build_index(&text, &index).unwrap(), pwned.len() as u64);
+
+        let items = [
+            input(1, "k9#Vq!2xLm@8Zr"),
+            input(2, "pwned0"),
+            input(3, "password"),
+            input(4, "pwned199"),
+            input(5, ""),
+        ];
+        for path in [&text, &ranges, &index] {
+            let mut ds = Dataset::open(path).unwrap();
+            let report = check(&mut ds, &items).unwrap();
+            assert_eq!(report.checked, 5);
+            let found: Vec<(i64, u64)> =
+                report.breached.iter().map(|b| (b.entry.id, b.count)).collect();
+            assert_eq!(found, vec![(3, 9_000_000), (4, 200), (2, 1)], "{path:?}");
+        }
+    }
+
+    #[test]
+    fn unsorted_text_is_rejected() {
+        let dir = tempdir().unwrap();
+        let text = dir.path().join("pwned.txt");
+        let (a, b) = (sha1_hex("a"), sha1_hex("b"));
+        let (lo, hi) = if a < b { (a, b) } else { (b, a) };
+        std::fs::write(&text, format!("{hi}:1\n{lo}:2\n")).unwrap();
+        assert!(build_index(&text, &dir.path().join("x.idx")).is_err());
+    }
+
+    #[test]
+    fn text_lookup_rejects_bad_order_and_case() {
+        let dir = tempdir().unwrap();
+        let text = dir.path().join("pwned.txt");
+        let mut hashes: Vec<String> = (0..16).map(|i| sha1_hex(&format!("pwned{i}"))).collect();
+        hashes.sort();
+        let lookup = |lines: &[String], hash: &str| {
+            let body: String = lines.iter().map(|h| format!("{h}:1\n")).collect();
+            std::fs::write(&text, body).unwrap();
+            let full: [u8; 20] = parse_line(&format!("{hash}:1"), 40).unwrap().0.try_into().unwrap();
+            Dataset::open(&text).unwrap().lookup(&full)
+        };
+
+        // переставленная строка: поиск не отвечает «не найдено», а отклоняет файл
+        let mut swapped = hashes.clone();
+        swapped.swap(3, 12);
+        let mut rejected = 0;
+        for h in &hashes {
+            match lookup(&swapped, h) {
+                Ok(found) => assert_eq!(found, Some(1), "{h}"),
+                Err(e) => {
+                    assert!(matches!(e, VaultError::InvalidDataset(_)), "{e:?}");
+                    rejected += 1;
+                }
+            }
+        }
+        assert!(rejected > 0);
+
+        let lower: Vec<String> = hashes.iter().map(|h| h.to_lowercase()).collect();
+        assert!(matches!(
+            lookup(&lower, &hashes[5]),
+            Err(VaultError::InvalidDataset(_))
+        ));
+    }
+
+    #[test]
+    fn corrupt_index_count_is_rejected() {
+        let dir = tempdir().unwrap();
+        let index = dir.path().join("bad.idx");
+        for count in [u64::MAX, u64::MAX / RECORD_LEN, 2] {
+            let mut data = INDEX_MAGIC.to_vec();
+            data.push(INDEX_VERSION);
+            data.extend_from_slice(&count.to_le_bytes());
+            data.extend_from_slice(&[0; RECORD_LEN as usize]);
+            std::fs::write(&index, data).unwrap();
+            assert!(Dataset::open(&index).is_err(), "count {count}");
+        }
+    }
+}
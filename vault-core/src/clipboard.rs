@@ -0,0 +1,237 @@
+//! Копирование секретов в буфер обмена с автоочисткой. Значение попадает в
+//! системный буфер прямо из бэкенда, минуя webview; по таймеру буфер очищается, но только
+//! если в нём всё ещё наше значение — скопированное пользователем позже не трогаем.
+//! Для сравнения хранится SHA-1 значения, а не оно само.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use sha1::{Digest, Sha1};
+use tokio::task::JoinHandle;
+
+use super::db::{ResultT, VaultError};
+
+/// Через сколько очищать буфер, если вызывающий не указал своё время.
+pub const DEFAULT_CLEAR_AFTER: Duration = Duration::from_secs(20);
+
+/// Доступ к буферу обмена; в тестах подменяется.
+pub trait ClipboardBackend: Send {
+    fn get_text(&mut self) -> Option<String>;
+    fn set_text(&mut self, text: &str) -> Result<(), String>;
+    fn clear(&mut self) -> Result<(), String>;
+}
+
+/// Системный буфер обмена через `arboard`. Объект должен жить, пока значение
+/// нужно в буфере: на X11 содержимое пропадает вместе с владельцем.
+#[cfg(feature = "clipboard")]
+pub struct SystemClipboard(arboard::Clipboard);
+
+#[cfg(feature = "clipboard")]
+impl SystemClipboard {
+    pub fn new() -> ResultT<Self> {
+        arboard::Clipboard::new()
+            .map(Self)
+            .map_err(|e| VaultError::Other(format!("clipboard unavailable: {e}")))
+    }
+}
+
+#[cfg(feature = "clipboard")]
+impl ClipboardBackend for SystemClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        self.0.get_text().ok()
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        self.0.set_text(text).map_err(|e| e.to_string())
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.0.clear().map_err(|e| e.to_string())
+    }
+}
+
+struct Pending {
+    digest: [u8; 20],
+    /// Номер копирования: уже сработавший таймер старого копирования не тронет новое.
+    generation: u64,
+    timer: JoinHandle<()>,
+}
+
+struct State {
+    backend: Box<dyn ClipboardBackend>,
+    pending: Option<Pending>,
+    generation: u64,
+}
+
+impl State {
+    /// Очищает буфер, если в нём то, что скопировали мы, и забывает об этом.
+    /// `generation` — чей таймер сработал; `None` — очистить в любом случае.
+    fn clear_if_ours(&mut self, generation: Option<u64>) {
+        if generation.is_some_and(|g| self.pending.as_ref().is_none_or(|p| p.generation != g)) {
+            return;
+        }
+        let Some(p) = self.pending.take() else {
+            return;
+        };
+        p.timer.abort();
+        if let Some(mut current) = self.backend.get_text() {
+            if digest(&current) == p.digest {
+                let _ = self.backend.clear();
+            }
+            zeroize::Zeroize::zeroize(&mut current);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SecureClipboard {
+    state: Arc<Mutex<State>>,
+}
+
+impl SecureClipboard {
+    pub fn new(backend: impl ClipboardBackend + 'static) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                backend: Box::new(backend),
+                pending: None,
+                generation: 0,
+            })),
+        }
+    }
+
+    /// Кладёт `text` в буфер и заводит таймер очистки; прежний таймер отменяется.
+    /// Если положить не удалось, прежнее значение по-прежнему очистится в свой срок.
+    /// Вызывать внутри рантайма tokio.
+    pub fn copy(&self, text: &str, clear_after: Duration) -> ResultT<()> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .backend
+            .set_text(text)
+            .map_err(|e| VaultError::Other(format!("clipboard: {e}")))?;
+        if let Some(old) = state.pending.take() {
+            old.timer.abort();
+        }
+
+        state.generation += 1;
+        let generation = state.generation;
+        let this = self.clone();
+        let timer = tokio::spawn(async move {
+            tokio::time::sleep(clear_after).await;
+            this.state.lock().unwrap().clear_if_ours(Some(generation));
+        });
+        state.pending = Some(Pending {
+            digest: digest(text),
+            generation,
+            timer,
+        });
+        Ok(())
+    }
+
+    /// Очистить сейчас (например, при блокировке хранилища) и отменить таймер.
+    pub fn clear_now(&self) {
+        self.state.lock().unwrap().clear_if_ours(None);
+    }
+}
+
+fn digest(text: &str) -> [u8; 20] {
+    Sha1::digest(text.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+
+    /// Буфер в памяти; с `fail` отказывается принимать новые значения.
+    #[derive(Clone, Default)]
+    struct Fake(Arc<Mutex<Option<String>>>, Arc<AtomicBool>);
+
+    impl ClipboardBackend for Fake {
+        fn get_text(&mut self) -> Option<String> {
+            self.0.lock().unwrap().clone()
+        }
+        fn set_text(&mut self, text: &str) -> Result<(), String> {
+            if self.1.load(Ordering::Relaxed) {
+                return Err("clipboard is busy".into());
+            }
+            *self.0.lock().unwrap() = Some(text.to_owned());
+            Ok(())
+        }
+        fn clear(&mut self) -> Result<(), String> {
+            *self.0.lock().unwrap() = None;
+            Ok(())
+        }
+    }
+
+    impl Fake {
+        fn text(&self) -> Option<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    const SHORT: Duration = Duration::from_millis(50);
+
+    async fn wait() {
+        tokio::time::sleep(SHORT * 3).await;
+    }
+
+    #[tokio::test]
+    async fn clears_only_its_own_value() {
+        let fake = Fake::default();
+        let clip = SecureClipboard::new(fake.clone());
+
+        clip.copy("s3cret", SHORT).unwrap();
+        assert_eq!(fake.text().as_deref(), Some("s3cret"));
+        wait().await;
+        assert_eq!(fake.text(), None);
+
+        // пользователь успел скопировать что-то своё — не трогаем
+        clip.copy("s3cret", SHORT).unwrap();
+        fake.clone().set_text("shopping list").unwrap();
+        wait().await;
+        assert_eq!(fake.text().as_deref(), Some("shopping list"));
+    }
+
+    #[tokio::test]
+    async fn new_copy_and_lock_cancel_the_timer() {
+        let fake = Fake::default();
+        let clip = SecureClipboard::new(fake.clone());
+
+        // второе копирование продлевает срок
+        clip.copy("one", SHORT).unwrap();
+        clip.copy("two", Duration::from_secs(60)).unwrap();
+        wait().await;
+        assert_eq!(fake.text().as_deref(), Some("two"));
+
+        // блокировка очищает сразу, а старый таймер больше не сработает
+        clip.clear_now();
+        assert_eq!(fake.text(), None);
+        fake.clone().set_text("two").unwrap();
+        clip.clear_now();
+        assert_eq!(fake.text().as_deref(), Some("two"));
+    }
+
+    #[tokio::test]
+    async fn failed_copy_keeps_the_previous_timer() {
+        let fake = Fake::default();
+        let clip = SecureClipboard::new(fake.clone());
+
+        clip.copy("s3cret", SHORT).unwrap();
+        fake.1.store(true, Ordering::Relaxed);
+        assert!(clip.copy("other", Duration::from_secs(60)).is_err());
+        assert_eq!(fake.text().as_deref(), Some("s3cret"));
+        wait().await;
+        assert_eq!(fake.text(), None);
+
+        // и блокировка его по-прежнему очищает
+        fake.1.store(false, Ordering::Relaxed);
+        clip.copy("s3cret", Duration::from_secs(60)).unwrap();
+        fake.1.store(true, Ordering::Relaxed);
+        assert!(clip.copy("other", Duration::from_secs(60)).is_err());
+        clip.clear_now();
+        assert_eq!(fake.text(), None);
+    }
+}
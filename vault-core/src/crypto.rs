@@ -0,0 +1,69 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use getrandom::getrandom;
+use serde::{Deserialize, Serialize};
+
+use super::db::{ResultT, VaultError};
+
+/// Параметры Argon2id; хранятся рядом с солью, чтобы ключ можно было вывести заново.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub mem_cost_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            mem_cost_kib: 19_456,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+pub fn argon2_from_params(p: &KdfParams) -> Argon2<'static> {
+    let params = Params::new(p.mem_cost_kib, p.iterations, p.parallelism, None).unwrap();
+    Argon2::new_with_secret(&[], Algorithm::Argon2id, Version::V0x13, params).unwrap()
+}
+
+/// nonce||ciphertext
+pub fn encrypt(key_bytes: &[u8; 32], plaintext: &[u8]) -> ResultT<Vec<u8>> {
+    encrypt_aad(key_bytes, plaintext, &[])
+}
+
+/// ожидает nonce||ciphertext
+pub fn decrypt(key_bytes: &[u8; 32], data: &[u8]) -> ResultT<Vec<u8>> {
+    decrypt_aad(key_bytes, data, &[])
+}
+
+/// nonce||ciphertext, `aad` аутентифицируется, но не шифруется
+pub fn encrypt_aad(key_bytes: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> ResultT<Vec<u8>> {
+    let key = Key::from_slice(key_bytes);
+    let cipher = ChaCha20Poly1305::new(key);
+
+    let mut nonce_bytes = [0u8; 12];
+    getrandom(&mut nonce_bytes).map_err(|_| VaultError::Crypto)?;
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let mut out = nonce_bytes.to_vec();
+    let ct = cipher
+        .encrypt(nonce, Payload { msg: plaintext, aad })
+        .map_err(|_| VaultError::Crypto)?;
+    out.extend_from_slice(&ct);
+    Ok(out)
+}
+
+pub fn decrypt_aad(key_bytes: &[u8; 32], data: &[u8], aad: &[u8]) -> ResultT<Vec<u8>> {
+    if data.len() < 12 {
+        return Err(VaultError::Crypto);
+    }
+    let (nonce_bytes, ct) = data.split_at(12);
+    let key = Key::from_slice(key_bytes);
+    let cipher = ChaCha20Poly1305::new(key);
+    cipher
+        .decrypt(Nonce::from_slice(nonce_bytes), Payload { msg: ct, aad })
+        .map_err(|_| VaultError::BadMasterPassword)
+}
@@ -0,0 +1,2518 @@
+use std::{
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use getrandom::getrandom;
+use rand::{rngs::StdRng, SeedableRng};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePool, SqlitePoolOptions, SqliteRow},
+    Executor, QueryBuilder, Row,
+};
+use thiserror::Error;
+use tokio::sync::RwLock;
+use zeroize::{Zeroize, Zeroizing};
+
+use super::audit::{self, AuditEntryRef, AuditInput, AuditOptions, AuditReport};
+use super::autobackup::{self, BackupSchedule, BackupStatus, StoredBackup};
+use super::backup::{self, BackupEntry, BackupPayload, BackupReport};
+use super::breach::{self, BreachReport};
+use super::crypto::{argon2_from_params, decrypt, encrypt, KdfParams};
+use super::export::{self, ExportFormat};
+use super::generator::{self, GeneratedPassword, GeneratorOptions};
+use super::migrations;
+use super::passphrase::{self, PassphraseOptions};
+use super::profiles::{self, GeneratorProfile};
+use super::query::{EntryPage, ListQuery, SortKey};
+use super::rotation::{self, RotationReminder};
+use super::search::{self, SearchHit, SearchOptions, SearchResults};
+use super::strength;
+
+#[derive(Debug, Error)]
+pub enum VaultError {
+    #[error("sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("vault not initialized with a master password")]
+    NotInitialized,
+    #[error("vault already initialized")]
+    AlreadyInitialized,
+    #[error("vault is locked")]
+    Locked,
+    #[error("entry {id} not found")]
+    NotFound { id: i64 },
+    #[error("entry {id} was changed elsewhere, current revision is {revision}")]
+    Conflict { id: i64, revision: i64 },
+    #[error("invalid master password")]
+    BadMasterPassword,
+    #[error("crypto error")]
+    Crypto,
+    #[error("database schema version {found} is newer than this app supports ({supported})")]
+    UnsupportedSchema { found: u32, supported: u32 },
+    #[error("invalid breach dataset: {0}")]
+    InvalidDataset(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl VaultError {
+    /// Стабильный машинный код ошибки: по нему UI выбирает текст и реакцию,
+    /// формулировки `Display` могут меняться.
+    pub fn code(&self) -> &'static str {
+        match self {
+            VaultError::Sqlx(_) => "database",
+            VaultError::NotInitialized => "not_initialized",
+            VaultError::AlreadyInitialized => "already_initialized",
+            VaultError::Locked => "locked",
+            VaultError::NotFound { .. } => "not_found",
+            VaultError::Conflict { .. } => "conflict",
+            VaultError::BadMasterPassword => "bad_password",
+            VaultError::Crypto => "crypto",
+            VaultError::UnsupportedSchema { .. } => "unsupported_schema",
+            VaultError::InvalidDataset(_) => "invalid_dataset",
+            VaultError::Other(_) => "other",
+        }
+    }
+
+    /// Параметры ошибки для подстановки в локализованный текст.
+    pub fn details(&self) -> serde_json::Value {
+        match self {
+            VaultError::UnsupportedSchema { found, supported } => {
+                serde_json::json!({ "found": found, "supported": supported })
+            }
+            VaultError::NotFound { id } => serde_json::json!({ "id": id }),
+            VaultError::Conflict { id, revision } => {
+                serde_json::json!({ "id": id, "revision": revision })
+            }
+            _ => serde_json::Value::Null,
+        }
+    }
+}
+
+/// Сериализуется как `{ code, message, details }` — в таком виде ошибки
+/// получают команды Tauri и вывод `--json`.
+impl Serialize for VaultError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut s = serializer.serialize_struct("VaultError", 3)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.serialize_field("details", &self.details())?;
+        s.end()
+    }
+}
+
+pub type ResultT<T> = Result<T, VaultError>;
+
+const KEY_CHECK_PLAINTEXT: &[u8] = b"vault-key-check";
+
+#[derive(Clone)]
+pub struct DataBase {
+    pool: SqlitePool,
+    key: Arc<RwLock<Option<[u8; 32]>>>,
+    /// Изменения записей с последнего автоматического бэкапа.
+    changes: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: i64,
+    pub site: String,
+    pub username: String,
+    pub password: String,
+    pub notes: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// Растёт с каждым изменением; передаётся обратно в `update_entry`.
+    pub revision: i64,
+}
+
+/// Расписание автобэкапов с расшифрованным ключом; ключ затирается при drop.
+struct ScheduleState {
+    schedule: BackupSchedule,
+    key: [u8; 32],
+    salt: [u8; 16],
+    params: KdfParams,
+    last_run_at: Option<i64>,
+}
+
+impl Drop for ScheduleState {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// Поле записи, которое можно показать или скопировать по отдельности.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryField {
+    Site,
+    Username,
+    Password,
+    Notes,
+}
+
+/// Итог импорта бэкапа.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryListItem {
+    pub id: i64,
+    pub site: String,
+    pub username: String,
+    /// Оценка стойкости пароля 0–4 (см. `strength.rs`).
+    pub strength: Option<u8>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub revision: i64,
+    /// Когда пароль последний раз показывали или копировали.
+    pub last_used_at: Option<i64>,
+    pub use_count: i64,
+    pub favorite: bool,
+    /// Место среди закреплённых записей; `None` — не закреплена.
+    pub pin_order: Option<i64>,
+}
+
+/// Запись без секретов: метаданные и есть ли заметки. Пароль и заметки
+/// отдаются отдельно, через `entry_field`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryView {
+    #[serde(flatten)]
+    pub meta: EntryListItem,
+    pub has_notes: bool,
+}
+
+impl DataBase {
+    /// Открывает базу, создавая файл, если его нет.
+    pub async fn open<P: AsRef<Path>>(path: P) -> ResultT<Self> {
+        Self::connect(path.as_ref(), true).await
+    }
+
+    /// Открывает только существующую базу: для команд, которым нечего делать
+    /// с пустым хранилищем, лишний файл на диске не нужен.
+    pub async fn open_existing<P: AsRef<Path>>(path: P) -> ResultT<Self> {
+        Self::connect(path.as_ref(), false).await
+    }
+
+    async fn connect(path: &Path, create: bool) -> ResultT<Self> {
+        let uri = format!("sqlite://{}", path.to_string_lossy());
+        let opts = SqliteConnectOptions::from_str(&uri)?.create_if_missing(create);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(opts)
+            .await?;
+
+        pool.execute("PRAGMA journal_mode=WAL;").await?;
+        migrations::migrate(&pool).await?;
+
+        Ok(Self {
+            pool,
+            key: Arc::new(RwLock::new(None)),
+            changes: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    pub async fn init_master(&self, master: SecretString) -> ResultT<()> {
+        if self.is_initialized().await? {
+            return Err(VaultError::AlreadyInitialized);
+        }
+        let mut conn = self.pool.acquire().await?;
+        let key = write_master_config(&mut conn, &master).await?;
+        *self.key.write().await = Some(key);
+        Ok(())
+    }
+
+    pub async fn unlock(&self, master: SecretString) -> ResultT<()> {
+        let key = self.derive_checked_key(&master).await?;
+        *self.key.write().await = Some(key);
+        // хранилище уже открыто: неудача досчёта не повод сообщать, что пароль не подошёл;
+        // записи без оценки останутся и досчитаются при следующей разблокировке
+        let _ = self.backfill_strength(&key).await;
+        Ok(())
+    }
+
+    /// Досчитывает оценку стойкости для записей, сохранённых до её появления.
+    /// Запись, которую не удалось расшифровать, пропускается.
+    async fn backfill_strength(&self, key: &[u8; 32]) -> ResultT<()> {
+        let rows = sqlx::query(
+            "SELECT id, site, username, password_enc FROM entries WHERE strength IS NULL",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        for r in rows {
+            let Ok(mut pwd) = decrypt(key, &r.get::<Vec<u8>, _>("password_enc")) else {
+                continue;
+            };
+            let score = strength_score(
+                &String::from_utf8_lossy(&pwd),
+                r.get("site"),
+                r.get("username"),
+            );
+            pwd.zeroize();
+            sqlx::query("UPDATE entries SET strength=? WHERE id=?")
+                .bind(score)
+                .bind(r.get::<i64, _>("id"))
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Оценка стойкости пароля без сохранения; сайт и логин учитываются как очевидные слова.
+    pub fn estimate_strength(
+        &self,
+        password: &str,
+        site: Option<&str>,
+        username: Option<&str>,
+    ) -> strength::StrengthReport {
+        let inputs: Vec<&str> = site.into_iter().chain(username).collect();
+        strength::estimate(password, &inputs)
+    }
+
+    /// Выводит ключ из мастер-пароля и сверяет его с `key_check`.
+    async fn derive_checked_key(&self, master: &SecretString) -> ResultT<[u8; 32]> {
+        let row =
+            sqlx::query("SELECT kdf_salt, kdf_params, key_check FROM vault_config WHERE id=1")
+                .fetch_optional(&self.pool)
+                .await?
+                .ok_or(VaultError::NotInitialized)?;
+
+        let salt: Vec<u8> = row.get("kdf_salt");
+        let kdf_params: KdfParams = serde_json::from_str(&row.get::<String, _>("kdf_params"))
+            .map_err(|e| VaultError::Other(e.to_string()))?;
+        let key_check: Vec<u8> = row.get("key_check");
+
+        let argon = argon2_from_params(&kdf_params);
+        let mut key = [0u8; 32];
+        argon
+            .hash_password_into(master.expose_secret().as_bytes(), &salt, &mut key)
+            .map_err(|_| VaultError::Crypto)?;
+
+        let check_plain = match decrypt(&key, &key_check) {
+            Ok(p) => p,
+            Err(e) => {
+                key.zeroize();
+                return Err(e);
+            }
+        };
+        if check_plain != KEY_CHECK_PLAINTEXT {
+            key.zeroize();
+            return Err(VaultError::BadMasterPassword);
+        }
+        Ok(key)
+    }
+
+    pub async fn lock(&self) {
+        if let Some(mut k) = self.key.write().await.take() {
+            k.zeroize();
+        }
+    }
+    pub async fn is_unlocked(&self) -> bool {
+        self.key.read().await.is_some()
+    }
+
+    pub async fn add_entry(
+        &self,
+        site: &str,
+        username: &str,
+        password: &str,
+        notes: Option<&str>,
+    ) -> ResultT<i64> {
+        let key = self.get_key().await?;
+        let now = epoch();
+
+        let pwd_ct = encrypt(&key, password.as_bytes())?;
+        let notes_ct = match notes {
+            Some(n) if !n.is_empty() => Some(encrypt(&key, n.as_bytes())?),
+            _ => None,
+        };
+
+        let res = sqlx::query(
+            "INSERT INTO entries (site, username, password_enc, notes_enc, strength, created_at, updated_at, password_changed_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(site)
+        .bind(username)
+        .bind(pwd_ct)
+        .bind(notes_ct)
+        .bind(strength_score(password, site, username))
+        .bind(now)
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        self.changes.fetch_add(1, Ordering::Relaxed);
+        Ok(res.last_insert_rowid())
+    }
+
+    pub async fn get_entry(&self, id: i64) -> ResultT<Entry> {
+        let key = self.get_key().await?;
+        let row = sqlx::query(
+            "SELECT id, site, username, password_enc, notes_enc, created_at, updated_at, revision
+             FROM entries WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(VaultError::NotFound { id })?;
+
+        let pwd_ct: Vec<u8> = row.get("password_enc");
+        let password =
+            String::from_utf8(decrypt(&key, &pwd_ct)?).map_err(|_| VaultError::Crypto)?;
+        let notes = match row.get::<Option<Vec<u8>>, _>("notes_enc") {
+            Some(ct) => Some(String::from_utf8(decrypt(&key, &ct)?).map_err(|_| VaultError::Crypto)?),
+            None => None,
+        };
+
+        self.record_use(id).await?;
+
+        Ok(Entry {
+            id: row.get("id"),
+            site: row.get("site"),
+            username: row.get("username"),
+            password,
+            notes,
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            revision: row.get("revision"),
+        })
+    }
+
+    /// Запись без пароля и заметок: ничего не расшифровывает и не считается
+    /// использованием. Как и `get_entry`, требует разблокированного хранилища.
+    pub async fn entry_view(&self, id: i64) -> ResultT<EntryView> {
+        self.get_key().await?;
+        let row = sqlx::query(
+            "SELECT id, site, username, strength, created_at, updated_at, revision,
+                    last_used_at, use_count, favorite, pin_order, notes_enc IS NOT NULL AS has_notes
+             FROM entries WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(VaultError::NotFound { id })?;
+        Ok(EntryView {
+            meta: list_item(&row),
+            has_notes: row.get("has_notes"),
+        })
+    }
+
+    /// Отмечает, что пароль записи показали или скопировали. Это не правка:
+    /// ревизия, `updated_at` и счётчик изменений для автобэкапа не меняются.
+    pub async fn record_use(&self, id: i64) -> ResultT<()> {
+        let updated =
+            sqlx::query("UPDATE entries SET last_used_at = ?, use_count = use_count + 1 WHERE id = ?")
+                .bind(epoch())
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+        if updated.rows_affected() == 0 {
+            return Err(VaultError::NotFound { id });
+        }
+        Ok(())
+    }
+
+    /// Добавляет запись в избранное или убирает из него (а заодно и из закреплённых).
+    pub async fn set_favorite(&self, id: i64, favorite: bool) -> ResultT<()> {
+        let updated = sqlx::query(
+            "UPDATE entries SET favorite = ?,
+             pin_order = CASE WHEN ? THEN pin_order ELSE NULL END WHERE id = ?",
+        )
+        .bind(favorite)
+        .bind(favorite)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(VaultError::NotFound { id });
+        }
+        Ok(())
+    }
+
+    /// Задаёт закреплённые записи целиком и в нужном порядке; остальные открепляются.
+    /// Закреплённая запись всегда в избранном. Неизвестный id — `NotFound`, и ничего не меняется.
+    pub async fn set_pins(&self, ids: &[i64]) -> ResultT<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE entries SET pin_order = NULL WHERE pin_order IS NOT NULL")
+            .execute(&mut *tx)
+            .await?;
+        for (pos, &id) in ids.iter().enumerate() {
+            let updated = sqlx::query("UPDATE entries SET pin_order = ?, favorite = 1 WHERE id = ?")
+                .bind(pos as i64)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            if updated.rows_affected() == 0 {
+                return Err(VaultError::NotFound { id });
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Все записи, отфильтрованные по подстроке; короткая форма `query_entries`.
+    /// `weakest_first` сортирует по оценке стойкости (слабые сверху, без оценки — в конце).
+    pub async fn list_entries(
+        &self,
+        search: Option<&str>,
+        weakest_first: bool,
+    ) -> ResultT<Vec<EntryListItem>> {
+        let query = ListQuery {
+            search: search.map(str::to_owned),
+            sort: if weakest_first {
+                SortKey::Strength
+            } else {
+                SortKey::Updated
+            },
+            descending: !weakest_first,
+            ..Default::default()
+        };
+        Ok(self.query_entries(&query).await?.items)
+    }
+
+    /// Страница списка записей с фильтрами и сортировкой (см. `query.rs`).
+    pub async fn query_entries(&self, query: &ListQuery) -> ResultT<EntryPage> {
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM entries");
+        query.push_filters(&mut count);
+        let total: i64 = count.build().fetch_one(&self.pool).await?.get(0);
+
+        let mut select = QueryBuilder::new(
+            "SELECT id, site, username, strength, created_at, updated_at, revision,
+                    last_used_at, use_count, favorite, pin_order
+             FROM entries",
+        );
+        query.push_filters(&mut select);
+        query.push_page(&mut select);
+        let rows = select.build().fetch_all(&self.pool).await?;
+        Ok(EntryPage {
+            total: total as usize,
+            items: rows.iter().map(list_item).collect(),
+        })
+    }
+
+    /// Нечёткий поиск по сайту, логину и (по желанию) заметкам, см. `search.rs`.
+    /// Заметки расшифровываются в памяти и только у разблокированного хранилища.
+    pub async fn search_entries(&self, opts: &SearchOptions) -> ResultT<SearchResults> {
+        let key = if opts.in_notes {
+            Some(self.get_key().await?)
+        } else {
+            None
+        };
+        let rows = sqlx::query(
+            "SELECT id, site, username, strength, created_at, updated_at, revision,
+                    last_used_at, use_count, favorite, pin_order, notes_enc
+             FROM entries",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut hits = Vec::new();
+        for r in &rows {
+            // нерасшифровываемая заметка не должна ронять весь поиск:
+            // такая запись ищется только по сайту и логину
+            let mut notes = match (&key, r.get::<Option<Vec<u8>>, _>("notes_enc")) {
+                (Some(key), Some(ct)) => {
+                    decrypt(key, &ct).ok().and_then(|p| String::from_utf8(p).ok())
+                }
+                _ => None,
+            };
+            let matched = search::score(
+                &opts.query,
+                r.get("site"),
+                r.get("username"),
+                notes.as_deref(),
+            );
+            notes.zeroize();
+            if let Some((field, kind, score)) = matched {
+                hits.push(SearchHit {
+                    entry: list_item(r),
+                    field,
+                    kind,
+                    score,
+                });
+            }
+        }
+        Ok(search::page(hits, opts))
+    }
+
+    /// Изменяет запись, если с момента чтения её никто не менял: `revision` — ревизия,
+    /// которую видел редактирующий, иначе `Conflict` с текущей. Возвращает новую ревизию.
+    pub async fn update_entry(
+        &self,
+        id: i64,
+        revision: i64,
+        site: &str,
+        username: &str,
+        password: Option<&str>,
+        notes: Option<&str>,
+    ) -> ResultT<i64> {
+        let key = self.get_key().await?;
+        let now = epoch();
+
+        let row = sqlx::query("SELECT password_enc, notes_enc, revision FROM entries WHERE id=?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(VaultError::NotFound { id })?;
+        let current: i64 = row.get("revision");
+        if current != revision {
+            return Err(VaultError::Conflict {
+                id,
+                revision: current,
+            });
+        }
+        let mut pwd_ct: Vec<u8> = row.get("password_enc");
+        let mut notes_ct: Option<Vec<u8>> = row.get("notes_enc");
+
+        let score = match password {
+            Some(p) => {
+                pwd_ct = encrypt(&key, p.as_bytes())?;
+                strength_score(p, site, username)
+            }
+            None => {
+                let mut cur = decrypt(&key, &pwd_ct)?;
+                let score = strength_score(&String::from_utf8_lossy(&cur), site, username);
+                cur.zeroize();
+                score
+            }
+        };
+        if let Some(n) = notes {
+            notes_ct = if n.is_empty() {
+                None
+            } else {
+                Some(encrypt(&key, n.as_bytes())?)
+            };
+        }
+
+        let updated = sqlx::query(
+            "UPDATE entries SET site=?, username=?, password_enc=?, notes_enc=?, strength=?, updated_at=?,
+             password_changed_at = CASE WHEN ? THEN ? ELSE password_changed_at END,
+             revision = revision + 1 WHERE id=? AND revision=?"
+        )
+        .bind(site).bind(username).bind(pwd_ct).bind(notes_ct).bind(score).bind(now)
+        .bind(password.is_some()).bind(now).bind(id).bind(revision)
+        .execute(&self.pool).await?;
+        // между чтением и записью запись могли изменить или удалить
+        if updated.rows_affected() == 0 {
+            let current = sqlx::query("SELECT revision FROM entries WHERE id=?")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+            return Err(match current {
+                Some(r) => VaultError::Conflict {
+                    id,
+                    revision: r.get("revision"),
+                },
+                None => VaultError::NotFound { id },
+            });
+        }
+        self.changes.fetch_add(1, Ordering::Relaxed);
+        Ok(revision + 1)
+    }
+
+    /// Удаляет запись; `NotFound`, если удалять было нечего.
+    pub async fn delete_entry(&self, id: i64) -> ResultT<()> {
+        let deleted = sqlx::query("DELETE FROM entries WHERE id=?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(VaultError::NotFound { id });
+        }
+        self.changes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Интервал ротации пароля в днях; `None` — без ротации.
+    pub async fn set_entry_rotation(&self, id: i64, rotate_days: Option<u32>) -> ResultT<()> {
+        if rotate_days == Some(0) {
+            return Err(VaultError::Other("rotation interval must be at least one day".into()));
+        }
+        let updated = sqlx::query("UPDATE entries SET rotate_days = ? WHERE id = ?")
+            .bind(rotate_days)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        if updated.rows_affected() == 0 {
+            return Err(VaultError::NotFound { id });
+        }
+        // интервал ротации входит в бэкап
+        self.changes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Записи с ротацией, срок которых истёк или истекает в ближайшие `within_days` дней;
+    /// самые просроченные — первыми.
+    pub async fn rotation_due(&self, within_days: u32) -> ResultT<Vec<RotationReminder>> {
+        self.get_key().await?;
+        let rows = sqlx::query(
+            "SELECT id, site, username, rotate_days, password_changed_at FROM entries
+             WHERE rotate_days IS NOT NULL",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let now = epoch();
+        let mut due: Vec<RotationReminder> = rows
+            .into_iter()
+            .filter_map(|r| {
+                let entry = AuditEntryRef {
+                    id: r.get("id"),
+                    site: r.get("site"),
+                    username: r.get("username"),
+                };
+                let days: i64 = r.get("rotate_days");
+                rotation::reminder(entry, days as u32, r.get("password_changed_at"), now, within_days)
+            })
+            .collect();
+        due.sort_by_key(|d| d.expires_at);
+        Ok(due)
+    }
+
+    /// Расшифровывает все записи в память — общая часть экспорта.
+    async fn decrypt_all(&self, key: &[u8; 32]) -> ResultT<Vec<BackupEntry>> {
+        let rows = sqlx::query(
+            "SELECT site, username, password_enc, notes_enc, created_at, updated_at, rotate_days
+             FROM entries ORDER BY id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut items = Vec::with_capacity(rows.len());
+        for r in rows {
+            let pwd_ct: Vec<u8> = r.get("password_enc");
+            let password =
+                String::from_utf8(decrypt(key, &pwd_ct)?).map_err(|_| VaultError::Crypto)?;
+            let notes = match r.get::<Option<Vec<u8>>, _>("notes_enc") {
+                Some(ct) => {
+                    Some(String::from_utf8(decrypt(key, &ct)?).map_err(|_| VaultError::Crypto)?)
+                }
+                None => None,
+            };
+            items.push(BackupEntry {
+                site: r.get("site"),
+                username: r.get("username"),
+                password,
+                notes,
+                created_at: r.get("created_at"),
+                updated_at: r.get("updated_at"),
+                rotate_days: r.get::<Option<i64>, _>("rotate_days").map(|d| d as u32),
+            });
+        }
+        Ok(items)
+    }
+
+    /// Проверка здоровья хранилища: все записи расшифровываются только в памяти,
+    /// в отчёт попадают ссылки на записи, но не пароли.
+    pub async fn vault_audit(&self, opts: &AuditOptions) -> ResultT<AuditReport> {
+        let items = self.audit_inputs().await?;
+        Ok(audit::build(&items, opts, epoch()))
+    }
+
+    /// Проверка паролей по локальной копии базы HIBP (текст, каталог диапазонов или индекс).
+    pub async fn check_breaches(&self, dataset: impl AsRef<Path>) -> ResultT<BreachReport> {
+        let items = self.audit_inputs().await?;
+        let mut ds = breach::Dataset::open(dataset.as_ref())?;
+        breach::check(&mut ds, &items)
+    }
+
+    async fn audit_inputs(&self) -> ResultT<Vec<AuditInput>> {
+        let key = self.get_key().await?;
+        let rows = sqlx::query(
+            "SELECT id, site, username, password_enc, updated_at FROM entries ORDER BY id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut items = Vec::with_capacity(rows.len());
+        for r in rows {
+            let pwd_ct: Vec<u8> = r.get("password_enc");
+            let password =
+                String::from_utf8(decrypt(&key, &pwd_ct)?).map_err(|_| VaultError::Crypto)?;
+            items.push(AuditInput {
+                entry: AuditEntryRef {
+                    id: r.get("id"),
+                    site: r.get("site"),
+                    username: r.get("username"),
+                },
+                password,
+                updated_at: r.get("updated_at"),
+            });
+        }
+        Ok(items)
+    }
+
+    /// Самодостаточный бэкап, зашифрованный паролем бэкапа (см. `backup.rs`).
+    pub async fn export_encrypted_bytes(&self, passphrase: &SecretString) -> ResultT<Vec<u8>> {
+        let key = self.get_key().await?;
+        let payload = BackupPayload {
+            created_at: epoch(),
+            entries: self.decrypt_all(&key).await?,
+        };
+        let mut json = serde_json::to_vec(&payload).unwrap();
+        let sealed = backup::seal(passphrase, &json);
+        json.zeroize();
+        sealed
+    }
+
+    /// Импорт бэкапа одной транзакцией: либо применяются все записи, либо ни одной.
+    /// Новый формат расшифровывается паролем бэкапа; если хранилище ещё не
+    /// инициализировано, этот пароль становится мастер-паролем (в той же транзакции).
+    /// Старые бэкапы (без заголовка) расшифровываются живым ключом хранилища.
+    pub async fn import_encrypted_bytes(
+        &self,
+        data: &[u8],
+        passphrase: &SecretString,
+    ) -> ResultT<ImportSummary> {
+        let fresh = backup::is_sealed(data) && !self.is_initialized().await?;
+        let plain = if backup::is_sealed(data) {
+            backup::open(passphrase, data)?
+        } else {
+            decrypt(&self.get_key().await?, data)?
+        };
+        self.apply_backup(plain, fresh.then_some(passphrase), false)
+            .await
+    }
+
+    /// Применяет расшифрованный бэкап в одной транзакции. `init_with` — мастер-пароль
+    /// для ещё не инициализированного хранилища; `replace` сначала удаляет все записи.
+    /// В обоих случаях записи бэкапа вставляются как есть, без слияния.
+    async fn apply_backup(
+        &self,
+        mut plain: Vec<u8>,
+        init_with: Option<&SecretString>,
+        replace: bool,
+    ) -> ResultT<ImportSummary> {
+        let mut tx = self.pool.begin().await?;
+        let applied = async {
+            let key = match init_with {
+                Some(master) => write_master_config(&mut tx, master).await?,
+                None => self.get_key().await?,
+            };
+            if replace {
+                sqlx::query("DELETE FROM entries").execute(&mut *tx).await?;
+            }
+            let dedupe = init_with.is_none() && !replace;
+            let summary = merge_entries(&mut tx, &key, &plain, dedupe).await?;
+            Ok::<_, VaultError>((key, summary))
+        }
+        .await;
+        plain.zeroize();
+
+        // при ошибке tx откатывается в Drop
+        let (key, summary) = applied?;
+        tx.commit().await?;
+        if init_with.is_some() {
+            *self.key.write().await = Some(key);
+        }
+        self.changes
+            .fetch_add((summary.inserted + summary.updated) as u64, Ordering::Relaxed);
+        Ok(summary)
+    }
+
+    pub async fn export_encrypted_backup<P: AsRef<Path>>(
+        &self,
+        path: P,
+        passphrase: &SecretString,
+    ) -> ResultT<()> {
+        let sealed = self.export_encrypted_bytes(passphrase).await?;
+        std::fs::write(path, sealed).map_err(|e| VaultError::Other(e.to_string()))
+    }
+
+    pub async fn import_encrypted_backup<P: AsRef<Path>>(
+        &self,
+        path: P,
+        passphrase: &SecretString,
+    ) -> ResultT<ImportSummary> {
+        let bytes = std::fs::read(path).map_err(|e| VaultError::Other(e.to_string()))?;
+        self.import_encrypted_bytes(&bytes, passphrase).await
+    }
+
+    /// Включает автоматические бэкапы. Ключ бэкапов выводится из `passphrase` один раз
+    /// и хранится зашифрованным ключом хранилища, так что планировщик работает,
+    /// пока хранилище разблокировано, не держа пароль в памяти.
+    pub async fn set_backup_schedule(
+        &self,
+        schedule: BackupSchedule,
+        passphrase: &SecretString,
+    ) -> ResultT<()> {
+        let key = self.get_key().await?;
+        if schedule.dir.trim().is_empty() {
+            return Err(VaultError::Other("backup directory is required".into()));
+        }
+        if schedule.interval_secs.is_none() && schedule.after_changes.is_none() {
+            return Err(VaultError::Other(
+                "backup schedule needs an interval or a change threshold".into(),
+            ));
+        }
+        if passphrase.expose_secret().is_empty() {
+            return Err(VaultError::Other("backup passphrase must not be empty".into()));
+        }
+        std::fs::create_dir_all(&schedule.dir).map_err(|e| VaultError::Other(e.to_string()))?;
+
+        let salt = backup::new_salt()?;
+        let params = KdfParams::default();
+        let mut backup_key = backup::derive_key(passphrase, &salt, &params)?;
+        let key_enc = encrypt(&key, &backup_key);
+        backup_key.zeroize();
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO backup_schedule (id, config, kdf_salt, kdf_params, key_enc, last_run_at)
+             VALUES (1, ?, ?, ?, ?, NULL)",
+        )
+        .bind(serde_json::to_string(&schedule).unwrap())
+        .bind(salt.to_vec())
+        .bind(serde_json::to_string(&params).unwrap())
+        .bind(key_enc?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn backup_schedule(&self) -> ResultT<Option<BackupSchedule>> {
+        let row = sqlx::query("SELECT config FROM backup_schedule WHERE id=1")
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|r| {
+            serde_json::from_str(&r.get::<String, _>("config"))
+                .map_err(|e| VaultError::Other(e.to_string()))
+        })
+        .transpose()
+    }
+
+    /// Время последнего бэкапа и последняя ошибка планировщика; `None` без расписания.
+    pub async fn backup_status(&self) -> ResultT<Option<BackupStatus>> {
+        let row = sqlx::query(
+            "SELECT last_run_at, last_error, last_error_at FROM backup_schedule WHERE id=1",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| BackupStatus {
+            last_run_at: r.get("last_run_at"),
+            last_error: r.get("last_error"),
+            last_error_at: r.get("last_error_at"),
+        }))
+    }
+
+    pub async fn clear_backup_schedule(&self) -> ResultT<()> {
+        sqlx::query("DELETE FROM backup_schedule")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Расписание вместе с расшифрованным ключом бэкапов.
+    async fn load_backup_schedule(&self) -> ResultT<Option<ScheduleState>> {
+        let key = self.get_key().await?;
+        let Some(row) = sqlx::query(
+            "SELECT config, kdf_salt, kdf_params, key_enc, last_run_at FROM backup_schedule WHERE id=1",
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+        let schedule: BackupSchedule = serde_json::from_str(&row.get::<String, _>("config"))
+            .map_err(|e| VaultError::Other(e.to_string()))?;
+        let params: KdfParams = serde_json::from_str(&row.get::<String, _>("kdf_params"))
+            .map_err(|e| VaultError::Other(e.to_string()))?;
+        let salt: [u8; 16] = row
+            .get::<Vec<u8>, _>("kdf_salt")
+            .try_into()
+            .map_err(|_| VaultError::Crypto)?;
+        let mut raw = decrypt(&key, &row.get::<Vec<u8>, _>("key_enc"))?;
+        let backup_key: Result<[u8; 32], _> = raw.as_slice().try_into();
+        raw.zeroize();
+        Ok(Some(ScheduleState {
+            schedule,
+            key: backup_key.map_err(|_| VaultError::Crypto)?,
+            salt,
+            params,
+            last_run_at: row.get("last_run_at"),
+        }))
+    }
+
+    /// Пора ли делать автоматический бэкап (только для разблокированного хранилища).
+    pub async fn backup_due(&self) -> ResultT<bool> {
+        if !self.is_unlocked().await {
+            return Ok(false);
+        }
+        let Some(state) = self.load_backup_schedule().await? else {
+            return Ok(false);
+        };
+        let schedule = &state.schedule;
+        let changes = self.changes.load(Ordering::Relaxed);
+
+        let by_time = match (schedule.interval_secs, state.last_run_at) {
+            (Some(_), None) => true,
+            (Some(secs), Some(last)) => epoch() - last >= secs as i64,
+            (None, _) => false,
+        };
+        let by_changes = schedule
+            .after_changes
+            .is_some_and(|n| n > 0 && changes >= n);
+        Ok(by_time || by_changes)
+    }
+
+    /// Пишет бэкап в каталог расписания и применяет ротацию.
+    pub async fn run_scheduled_backup(&self) -> ResultT<StoredBackup> {
+        let key = self.get_key().await?;
+        let state = self
+            .load_backup_schedule()
+            .await?
+            .ok_or_else(|| VaultError::Other("automatic backups are not configured".into()))?;
+        let schedule = &state.schedule;
+
+        let now = epoch();
+        let changes = self.changes.load(Ordering::Relaxed);
+        let payload = BackupPayload {
+            created_at: now,
+            entries: self.decrypt_all(&key).await?,
+        };
+        let mut json = serde_json::to_vec(&payload).unwrap();
+        let sealed = backup::seal_with_key(&state.key, &state.salt, &state.params, &json);
+        json.zeroize();
+        let sealed = sealed?;
+
+        let dir = Path::new(&schedule.dir);
+        std::fs::create_dir_all(dir).map_err(|e| VaultError::Other(e.to_string()))?;
+        let file_name = autobackup::free_file_name(dir, now)?;
+        // пишем во временный файл и переименовываем, чтобы не оставить обрезанный бэкап
+        let tmp = dir.join(format!(".{file_name}.tmp"));
+        export::write_private(&tmp, &sealed)?;
+        std::fs::rename(&tmp, dir.join(&file_name)).map_err(|e| VaultError::Other(e.to_string()))?;
+
+        sqlx::query(
+            "UPDATE backup_schedule SET last_run_at=?, last_error=NULL, last_error_at=NULL WHERE id=1",
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        self.changes.fetch_sub(changes, Ordering::Relaxed);
+
+        let stored = autobackup::list_dir(dir)?;
+        let stamps: Vec<i64> = stored.iter().map(|b| b.created_at).collect();
+        for i in autobackup::rotation_victims(&stamps, schedule.keep_daily, schedule.keep_weekly) {
+            std::fs::remove_file(dir.join(&stored[i].file_name))
+                .map_err(|e| VaultError::Other(e.to_string()))?;
+        }
+
+        Ok(StoredBackup {
+            file_name,
+            created_at: now,
+            size: sealed.len() as u64,
+        })
+    }
+
+    pub async fn list_stored_backups(&self) -> ResultT<Vec<StoredBackup>> {
+        match self.backup_schedule().await? {
+            Some(schedule) => autobackup::list_dir(Path::new(&schedule.dir)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub async fn verify_stored_backup(&self, file_name: &str) -> ResultT<BackupReport> {
+        let mut plain = self.open_stored_backup(file_name).await?;
+        let report = backup::inspect(backup::FORMAT_VERSION, &plain, epoch());
+        plain.zeroize();
+        Ok(report)
+    }
+
+    /// Заменяет содержимое хранилища сохранённым бэкапом (одной транзакцией).
+    pub async fn restore_stored_backup(&self, file_name: &str) -> ResultT<ImportSummary> {
+        let plain = self.open_stored_backup(file_name).await?;
+        self.apply_backup(plain, None, true).await
+    }
+
+    async fn open_stored_backup(&self, file_name: &str) -> ResultT<Vec<u8>> {
+        let state = self
+            .load_backup_schedule()
+            .await?
+            .ok_or_else(|| VaultError::Other("automatic backups are not configured".into()))?;
+        let path = autobackup::resolve(Path::new(&state.schedule.dir), file_name)?;
+        let data = std::fs::read(path).map_err(|e| VaultError::Other(e.to_string()))?;
+        backup::open_with_key(&state.key, &data)
+    }
+
+    /// Фоновый цикл планировщика: раз в `tick` проверяет, не пора ли сделать бэкап.
+    /// Ошибки не печатаются, а сохраняются в расписании (см. `backup_status`).
+    pub async fn run_backup_scheduler(self, tick: Duration) {
+        loop {
+            tokio::time::sleep(tick).await;
+            self.backup_tick().await;
+        }
+    }
+
+    async fn backup_tick(&self) {
+        let result = match self.backup_due().await {
+            Ok(true) => self.run_scheduled_backup().await.map(drop),
+            Ok(false) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            // сохранить не удалось — база недоступна; попробуем на следующем тике
+            let _ = sqlx::query(
+                "UPDATE backup_schedule SET last_error=?, last_error_at=? WHERE id=1",
+            )
+            .bind(e.to_string())
+            .bind(epoch())
+            .execute(&self.pool)
+            .await;
+        }
+    }
+
+    /// Незашифрованный экспорт для переезда или аудита. Мастер-пароль запрашивается
+    /// заново даже у разблокированного хранилища; файл создаётся с правами только
+    /// для владельца. Возвращает число выгруженных записей.
+    pub async fn export_plaintext<P: AsRef<Path>>(
+        &self,
+        path: P,
+        master: &SecretString,
+        format: ExportFormat,
+    ) -> ResultT<usize> {
+        let mut key = self.derive_checked_key(master).await?;
+        let items = self.decrypt_all(&key).await;
+        key.zeroize();
+        let items = items?;
+
+        let mut bytes = match format {
+            ExportFormat::Json => serde_json::to_vec_pretty(&items).unwrap(),
+            ExportFormat::Csv => export::to_csv(&items).into_bytes(),
+        };
+        let written = export::write_private(path.as_ref(), &bytes);
+        bytes.zeroize();
+        written?;
+        Ok(items.len())
+    }
+
+    /// Проверяет файл бэкапа, не трогая таблицу `entries`: аутентифицирует и разбирает
+    /// пейлоад. Неверный пароль — ошибка, структурные проблемы — в `problems`.
+    /// Старые бэкапы проверяются живым ключом хранилища, `passphrase` для них не нужен.
+    pub async fn verify_backup<P: AsRef<Path>>(
+        &self,
+        path: P,
+        passphrase: Option<&SecretString>,
+    ) -> ResultT<BackupReport> {
+        let data = std::fs::read(path).map_err(|e| VaultError::Other(e.to_string()))?;
+        let (version, mut plain) = if backup::is_sealed(&data) {
+            let passphrase = passphrase
+                .ok_or_else(|| VaultError::Other("backup passphrase is required".into()))?;
+            (backup::FORMAT_VERSION, backup::open(passphrase, &data)?)
+        } else {
+            (0, decrypt(&self.get_key().await?, &data)?)
+        };
+        let report = backup::inspect(version, &plain, epoch());
+        plain.zeroize();
+        Ok(report)
+    }
+
+    pub async fn is_initialized(&self) -> ResultT<bool> {
+        let row = sqlx::query("SELECT COUNT(*) as c FROM vault_config")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.get::<i64, _>("c") > 0)
+    }
+
+    /// Случайный пароль по параметрам (см. `generator.rs`); источник — ChaCha, засеянный из ОС.
+    /// Если к записи или к сайту привязан профиль, его правила важнее параметров.
+    pub async fn generate_password(
+        &self,
+        opts: &GeneratorOptions,
+        entry_id: Option<i64>,
+        site: Option<&str>,
+    ) -> ResultT<GeneratedPassword> {
+        let profile = self.profile_for(entry_id, site).await?;
+        let mut rng = StdRng::from_os_rng();
+        match profile {
+            Some(p) => profiles::generate(&p, opts, &mut rng),
+            None => generator::generate(opts, &mut rng),
+        }
+    }
+
+    /// Профиль для генерации: явно привязанный к записи, иначе самый точный
+    /// шаблон сайта (сайт берётся из записи, если не передан).
+    pub async fn profile_for(
+        &self,
+        entry_id: Option<i64>,
+        site: Option<&str>,
+    ) -> ResultT<Option<GeneratorProfile>> {
+        let mut site = site.map(str::to_owned);
+        if let Some(id) = entry_id {
+            let row = sqlx::query(
+                "SELECT e.site, p.profile FROM entries e
+                 LEFT JOIN generator_profiles p ON p.id = e.profile_id WHERE e.id = ?",
+            )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(VaultError::NotFound { id })?;
+            if let Some(json) = row.get::<Option<String>, _>("profile") {
+                return Ok(Some(parse_profile(&json)?));
+            }
+            site = site.or_else(|| Some(row.get("site")));
+        }
+        let Some(site) = site else {
+            return Ok(None);
+        };
+        let best = self
+            .list_profiles()
+            .await?
+            .into_iter()
+            .filter_map(|p| p.site_match(&site).map(|score| (score, p)))
+            .max_by_key(|(score, _)| *score)
+            .map(|(_, p)| p);
+        Ok(best)
+    }
+
+    pub async fn list_profiles(&self) -> ResultT<Vec<GeneratorProfile>> {
+        let rows = sqlx::query("SELECT profile FROM generator_profiles ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(|r| parse_profile(r.get("profile"))).collect()
+    }
+
+    /// Создаёт профиль (`id == 0`) или заменяет существующий; возвращает id.
+    pub async fn save_profile(&self, mut profile: GeneratorProfile) -> ResultT<i64> {
+        profile.validate()?;
+        profile.name = profile.name.trim().to_owned();
+        let mut tx = self.pool.begin().await?;
+        if profile.id == 0 {
+            profile.id = sqlx::query("INSERT INTO generator_profiles (name, profile) VALUES (?, '')")
+                .bind(&profile.name)
+                .execute(&mut *tx)
+                .await?
+                .last_insert_rowid();
+        }
+        let updated = sqlx::query("UPDATE generator_profiles SET name=?, profile=? WHERE id=?")
+            .bind(&profile.name)
+            .bind(serde_json::to_string(&profile).unwrap())
+            .bind(profile.id)
+            .execute(&mut *tx)
+            .await?;
+        if updated.rows_affected() == 0 {
+            return Err(VaultError::Other(format!("no profile with id {}", profile.id)));
+        }
+        tx.commit().await?;
+        Ok(profile.id)
+    }
+
+    pub async fn delete_profile(&self, id: i64) -> ResultT<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE entries SET profile_id = NULL WHERE profile_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM generator_profiles WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Привязывает профиль к записи; `None` — вернуться к шаблонам сайтов.
+    pub async fn set_entry_profile(&self, entry_id: i64, profile_id: Option<i64>) -> ResultT<()> {
+        if let Some(pid) = profile_id {
+            sqlx::query("SELECT 1 FROM generator_profiles WHERE id = ?")
+                .bind(pid)
+                .fetch_optional(&self.pool)
+                .await?
+                .ok_or_else(|| VaultError::Other(format!("no profile with id {pid}")))?;
+        }
+        let updated = sqlx::query("UPDATE entries SET profile_id = ? WHERE id = ?")
+            .bind(profile_id)
+            .bind(entry_id)
+            .execute(&self.pool)
+            .await?;
+        if updated.rows_affected() == 0 {
+            return Err(VaultError::NotFound { id: entry_id });
+        }
+        Ok(())
+    }
+
+    /// Парольная фраза из случайных слов (см. `passphrase.rs`).
+    pub fn generate_passphrase(&self, opts: &PassphraseOptions) -> ResultT<GeneratedPassword> {
+        let list = match &opts.wordlist {
+            Some(text) => passphrase::parse_wordlist(text)?,
+            None => passphrase::builtin_wordlist(),
+        };
+        let mut rng = StdRng::from_os_rng();
+        passphrase::generate(opts, &list, &mut rng)
+    }
+
+    /// Одно поле записи; пустые заметки — пустая строка. Значение затирается при drop.
+    /// Расшифровывается только запрошенное поле; использованием считаются
+    /// только пароль и заметки.
+    pub async fn entry_field(&self, id: i64, field: EntryField) -> ResultT<Zeroizing<String>> {
+        let key = self.get_key().await?;
+        let column = match field {
+            EntryField::Site => "site",
+            EntryField::Username => "username",
+            EntryField::Password => "password_enc",
+            EntryField::Notes => "notes_enc",
+        };
+        let row = sqlx::query(&format!("SELECT {column} FROM entries WHERE id = ?"))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(VaultError::NotFound { id })?;
+
+        let value = match field {
+            EntryField::Site | EntryField::Username => Zeroizing::new(row.get::<String, _>(0)),
+            EntryField::Password | EntryField::Notes => {
+                match row.get::<Option<Vec<u8>>, _>(0) {
+                    Some(ct) => Zeroizing::new(
+                        String::from_utf8(decrypt(&key, &ct)?).map_err(|_| VaultError::Crypto)?,
+                    ),
+                    None => Zeroizing::new(String::new()),
+                }
+            }
+        };
+        if matches!(field, EntryField::Password | EntryField::Notes) {
+            self.record_use(id).await?;
+        }
+        Ok(value)
+    }
+
+    pub async fn get_password(&self, id: i64) -> ResultT<String> {
+        Ok(self.get_entry(id).await?.password)
+    }
+
+    async fn get_key(&self) -> ResultT<[u8; 32]> {
+        self.key
+            .read()
+            .await
+            .as_ref()
+            .copied()
+            .ok_or(VaultError::Locked)
+    }
+}
+
+/// Записывает vault_config для нового мастер-пароля и возвращает выведенный ключ.
+async fn write_master_config(
+    conn: &mut SqliteConnection,
+    master: &SecretString,
+) -> ResultT<[u8; 32]> {
+    let mut salt = [0u8; 16];
+    getrandom(&mut salt).map_err(|_| VaultError::Crypto)?;
+
+    let kdf_params = KdfParams::default();
+    let argon = argon2_from_params(&kdf_params);
+
+    let mut key = [0u8; 32];
+    argon
+        .hash_password_into(master.expose_secret().as_bytes(), &salt, &mut key)
+        .map_err(|_| VaultError::Crypto)?;
+
+    let key_check = encrypt(&key, KEY_CHECK_PLAINTEXT)?;
+    let now = epoch();
+
+    sqlx::query(
+        "INSERT INTO vault_config (id, kdf_salt, kdf_params, key_check, created_at)
+         VALUES (1, ?, ?, ?, ?)",
+    )
+    .bind(salt.to_vec())
+    .bind(serde_json::to_string(&kdf_params).unwrap())
+    .bind(key_check)
+    .bind(now)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(key)
+}
+
+/// Новый формат — объект с метаданными, старый — голый массив записей.
+/// Записи остаются сырыми `Value`, чтобы битая запись ломала импорт на своём месте.
+fn parse_backup(plain: &[u8]) -> ResultT<Vec<serde_json::Value>> {
+    #[derive(Deserialize)]
+    struct RawPayload {
+        entries: Vec<serde_json::Value>,
+    }
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum AnyBackup {
+        Payload(RawPayload),
+        Legacy(Vec<serde_json::Value>),
+    }
+    match serde_json::from_slice(plain).map_err(|e| VaultError::Other(e.to_string()))? {
+        AnyBackup::Payload(p) => Ok(p.entries),
+        AnyBackup::Legacy(items) => Ok(items),
+    }
+}
+
+/// Сливает записи бэкапа с хранилищем по паре site/username:
+/// новая пара вставляется, более свежая версия перезаписывает старую,
+/// совпадающая или более старая пропускается. Сверка идёт только с записями,
+/// которые были до импорта: одинаковые пары внутри бэкапа — разные записи.
+/// Без `dedupe` вставляется всё как есть.
+async fn merge_entries(
+    conn: &mut SqliteConnection,
+    key: &[u8; 32],
+    plain: &[u8],
+    dedupe: bool,
+) -> ResultT<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    let before: i64 = if dedupe {
+        sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM entries")
+            .fetch_one(&mut *conn)
+            .await?
+    } else {
+        0
+    };
+    for (i, raw) in parse_backup(plain)?.into_iter().enumerate() {
+        let it: BackupEntry = serde_json::from_value(raw)
+            .map_err(|e| VaultError::Other(format!("backup item {i}: {e}")))?;
+        if it.site.trim().is_empty() {
+            return Err(VaultError::Other(format!("backup item {i}: empty site")));
+        }
+        let now = epoch();
+        let created_at = if it.created_at > 0 { it.created_at } else { now };
+        let updated_at = if it.updated_at > 0 { it.updated_at } else { created_at };
+
+        let existing = sqlx::query(
+            "SELECT id, password_enc, notes_enc, updated_at FROM entries
+             WHERE site = ? AND username = ? AND id <= ? ORDER BY updated_at DESC LIMIT 1",
+        )
+        .bind(&it.site)
+        .bind(&it.username)
+        .bind(before)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let pwd_ct = encrypt(key, it.password.as_bytes())?;
+        let score = strength_score(&it.password, &it.site, &it.username);
+        let notes_ct = match it.notes.as_deref() {
+            Some(n) if !n.is_empty() => Some(encrypt(key, n.as_bytes())?),
+            _ => None,
+        };
+
+        match existing {
+            None => {
+                sqlx::query(
+                    "INSERT INTO entries (site, username, password_enc, notes_enc, strength, created_at, updated_at, password_changed_at, rotate_days)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(&it.site)
+                .bind(&it.username)
+                .bind(pwd_ct)
+                .bind(notes_ct)
+                .bind(score)
+                .bind(created_at)
+                .bind(updated_at)
+                .bind(updated_at)
+                .bind(it.rotate_days)
+                .execute(&mut *conn)
+                .await?;
+                summary.inserted += 1;
+            }
+            Some(row) => {
+                let cur_pwd = decrypt(key, &row.get::<Vec<u8>, _>("password_enc"))?;
+                let cur_notes = match row.get::<Option<Vec<u8>>, _>("notes_enc") {
+                    Some(ct) => Some(decrypt(key, &ct)?),
+                    None => None,
+                };
+                let new_notes = it.notes.as_deref().filter(|n| !n.is_empty()).map(str::as_bytes);
+                let same_pwd = cur_pwd == it.password.as_bytes();
+                let same = same_pwd && cur_notes.as_deref() == new_notes;
+
+                if same || updated_at <= row.get::<i64, _>("updated_at") {
+                    summary.skipped += 1;
+                    continue;
+                }
+                sqlx::query(
+                    "UPDATE entries SET password_enc=?, notes_enc=?, strength=?, updated_at=?,
+                     password_changed_at = CASE WHEN ? THEN password_changed_at ELSE ? END,
+                     rotate_days = COALESCE(?, rotate_days), revision = revision + 1 WHERE id=?",
+                )
+                .bind(pwd_ct)
+                .bind(notes_ct)
+                .bind(score)
+                .bind(updated_at)
+                .bind(same_pwd)
+                .bind(updated_at)
+                .bind(it.rotate_days)
+                .bind(row.get::<i64, _>("id"))
+                .execute(&mut *conn)
+                .await?;
+                summary.updated += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+fn strength_score(password: &str, site: &str, username: &str) -> i64 {
+    strength::estimate(password, &[site, username]).score as i64
+}
+
+fn list_item(r: &SqliteRow) -> EntryListItem {
+    EntryListItem {
+        id: r.get("id"),
+        site: r.get("site"),
+        username: r.get("username"),
+        strength: r.get::<Option<i64>, _>("strength").map(|s| s as u8),
+        created_at: r.get("created_at"),
+        updated_at: r.get("updated_at"),
+        revision: r.get("revision"),
+        last_used_at: r.get("last_used_at"),
+        use_count: r.get("use_count"),
+        favorite: r.get("favorite"),
+        pin_order: r.get("pin_order"),
+    }
+}
+
+fn parse_profile(json: &str) -> ResultT<GeneratorProfile> {
+    serde_json::from_str(json).map_err(|e| VaultError::Other(format!("corrupt profile: {e}")))
+}
+
+fn epoch() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{tempdir, TempDir};
+
+    async fn unlocked_db() -> (TempDir, DataBase) {
+        let dir = tempdir().unwrap();
+        let db = DataBase::open(dir.path().join("t.db")).await.unwrap();
+        db.init_master(SecretString::new("master123".into()))
+            .await
+            .unwrap();
+        (dir, db)
+    }
+
+    #[tokio::test]
+    async fn end_to_end() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("t.db");
+        let db = DataBase::open(&db_path).await.unwrap();
+
+        db.init_master(SecretString::new("master123".into()))
+            .await
+            .unwrap();
+        assert!(db.is_unlocked().await);
+
+        let id = db
+            .add_entry("example.com", "alice", "p@ss", Some("note"))
+            .await
+            .unwrap();
+        let e = db.get_entry(id).await.unwrap();
+        assert_eq!(e.username, "alice");
+        assert_eq!(e.password, "p@ss");
+        assert_eq!(e.notes.as_deref(), Some("note"));
+
+        db.update_entry(id, 1, "example.org", "alice", Some("new"), None)
+            .await
+            .unwrap();
+        let e2 = db.get_entry(id).await.unwrap();
+        assert_eq!(e2.site, "example.org");
+        assert_eq!(e2.password, "new");
+        // `None` оставляет заметку, пустая строка её удаляет
+        assert_eq!(e2.notes.as_deref(), Some("note"));
+        db.update_entry(id, 2, "example.org", "alice", None, Some(""))
+            .await
+            .unwrap();
+        let e3 = db.get_entry(id).await.unwrap();
+        assert_eq!(e3.password, "new");
+        assert!(e3.notes.is_none());
+
+        let all = db.list_entries(None, false).await.unwrap();
+        assert_eq!(all.len(), 1);
+        let found = db.list_entries(Some("example"), false).await.unwrap();
+        assert_eq!(found.len(), 1);
+
+        let backup = dir.path().join("b.vault");
+        let pass = SecretString::new("backup-pass".into());
+        db.export_encrypted_backup(&backup, &pass).await.unwrap();
+        let summary = db.import_encrypted_backup(&backup, &pass).await.unwrap();
+        assert_eq!(summary.skipped, 1);
+
+        db.lock().await;
+        assert!(!db.is_unlocked().await);
+        assert!(matches!(db.get_entry(id).await, Err(VaultError::Locked)));
+        assert!(matches!(
+            db.unlock(SecretString::new("wrong".into())).await,
+            Err(VaultError::BadMasterPassword)
+        ));
+        db.unlock(SecretString::new("master123".into()))
+            .await
+            .unwrap();
+        let _ = db.get_entry(id).await.unwrap();
+
+        // данные переживают повторное открытие файла
+        drop(db);
+        let db = DataBase::open(&db_path).await.unwrap();
+        assert!(db.is_initialized().await.unwrap());
+        assert!(!db.is_unlocked().await);
+        assert!(matches!(
+            db.init_master(SecretString::new("other".into())).await,
+            Err(VaultError::AlreadyInitialized)
+        ));
+        db.unlock(SecretString::new("master123".into()))
+            .await
+            .unwrap();
+        assert_eq!(db.get_entry(id).await.unwrap().password, "new");
+
+        let g = db
+            .generate_password(&GeneratorOptions::default(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(g.password.chars().count(), 20);
+
+        db.delete_entry(id).await.unwrap();
+        assert!(db.list_entries(None, false).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn backup_restores_into_fresh_vault() {
+        let dir = tempdir().unwrap();
+        let src = DataBase::open(dir.path().join("src.db")).await.unwrap();
+        src.init_master(SecretString::new("master123".into()))
+            .await
+            .unwrap();
+        src.add_entry("example.com", "alice", "p@ss", Some("note"))
+            .await
+            .unwrap();
+        let pass = SecretString::new("backup-pass".into());
+        let sealed = src.export_encrypted_bytes(&pass).await.unwrap();
+
+        let dst = DataBase::open(dir.path().join("dst.db")).await.unwrap();
+        let wrong = SecretString::new("nope".into());
+        assert!(matches!(
+            dst.import_encrypted_bytes(&sealed, &wrong).await,
+            Err(VaultError::BadMasterPassword)
+        ));
+        assert!(!dst.is_initialized().await.unwrap());
+
+        let summary = dst.import_encrypted_bytes(&sealed, &pass).await.unwrap();
+        assert_eq!(summary.inserted, 1);
+        let items = dst.list_entries(None, false).await.unwrap();
+        let e = dst.get_entry(items[0].id).await.unwrap();
+        assert_eq!(e.password, "p@ss");
+        assert_eq!(e.notes.as_deref(), Some("note"));
+
+        dst.lock().await;
+        dst.unlock(SecretString::new("backup-pass".into()))
+            .await
+            .unwrap();
+
+        let mut tampered = sealed.clone();
+        tampered[backup::MAGIC.len() + 1] ^= 1;
+        assert!(dst.import_encrypted_bytes(&tampered, &pass).await.is_err());
+
+        // параметры KDF из заголовка не проверены до вывода ключа — их ограничиваем заранее
+        let kdf_at = backup::MAGIC.len() + 1 + backup::SALT_LEN;
+        for (offset, value) in [(0, u32::MAX), (4, 1_000_000), (8, 0xFF_FFFF)] {
+            let mut costly = sealed.clone();
+            costly[kdf_at + offset..kdf_at + offset + 4].copy_from_slice(&value.to_le_bytes());
+            assert!(matches!(
+                dst.import_encrypted_bytes(&costly, &pass).await,
+                Err(VaultError::Other(_))
+            ));
+            let path = dir.path().join("costly.vault");
+            std::fs::write(&path, &costly).unwrap();
+            assert!(dst.verify_backup(&path, Some(&pass)).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicate_logins_survive_backup_roundtrip() {
+        let dir = tempdir().unwrap();
+        let src = DataBase::open(dir.path().join("src.db")).await.unwrap();
+        src.init_master(SecretString::new("master123".into()))
+            .await
+            .unwrap();
+        src.add_entry("corp", "", "pw1", None).await.unwrap();
+        src.add_entry("corp", "", "pw2", Some("vpn")).await.unwrap();
+        let pass = SecretString::new("backup-pass".into());
+        let sealed = src.export_encrypted_bytes(&pass).await.unwrap();
+
+        // и в новую установку, и в пустое инициализированное хранилище
+        let fresh = DataBase::open(dir.path().join("fresh.db")).await.unwrap();
+        let empty = DataBase::open(dir.path().join("empty.db")).await.unwrap();
+        empty
+            .init_master(SecretString::new("master123".into()))
+            .await
+            .unwrap();
+        for db in [&fresh, &empty] {
+            let summary = db.import_encrypted_bytes(&sealed, &pass).await.unwrap();
+            assert_eq!(
+                summary,
+                ImportSummary { inserted: 2, updated: 0, skipped: 0 }
+            );
+            let mut passwords = Vec::new();
+            for it in db.list_entries(None, false).await.unwrap() {
+                passwords.push(db.get_entry(it.id).await.unwrap().password);
+            }
+            passwords.sort();
+            assert_eq!(passwords, ["pw1", "pw2"]);
+        }
+    }
+
+    fn sealed_items(pass: &SecretString, entries: serde_json::Value) -> Vec<u8> {
+        let payload = serde_json::json!({ "created_at": epoch(), "entries": entries });
+        backup::seal(pass, payload.to_string().as_bytes()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn import_merges_and_rolls_back() {
+        let (dir, db) = unlocked_db().await;
+        db.add_entry("a.com", "alice", "old", None).await.unwrap();
+        let pass = SecretString::new("backup-pass".into());
+
+        let good = sealed_items(
+            &pass,
+            serde_json::json!([
+                { "site": "a.com", "username": "alice", "password": "new", "notes": null,
+                  "created_at": 1, "updated_at": epoch() + 10 },
+                { "site": "b.com", "username": "bob", "password": "pw", "notes": "n" },
+                { "site": "b.com", "username": "bob", "password": "pw", "notes": "n" },
+            ]),
+        );
+        let summary = db.import_encrypted_bytes(&good, &pass).await.unwrap();
+        // одинаковые пары внутри бэкапа — разные записи, обе вставляются
+        assert_eq!(
+            summary,
+            ImportSummary { inserted: 2, updated: 1, skipped: 0 }
+        );
+        let items = db.list_entries(Some("a.com"), false).await.unwrap();
+        assert_eq!(db.get_entry(items[0].id).await.unwrap().password, "new");
+
+        // повторный импорт того же бэкапа ничего не добавляет
+        let again = db.import_encrypted_bytes(&good, &pass).await.unwrap();
+        assert_eq!(
+            again,
+            ImportSummary { inserted: 0, updated: 0, skipped: 3 }
+        );
+
+        // вторая запись битая: первая и третья не должны остаться в базе
+        let corrupt = sealed_items(
+            &pass,
+            serde_json::json!([
+                { "site": "c.com", "username": "carol", "password": "pw", "notes": null },
+                { "site": "d.com", "username": 42 },
+                { "site": "e.com", "username": "eve", "password": "pw", "notes": null },
+            ]),
+        );
+        assert!(db.import_encrypted_bytes(&corrupt, &pass).await.is_err());
+        assert_eq!(db.list_entries(None, false).await.unwrap().len(), 3);
+
+        // на новой установке откатывается и инициализация хранилища
+        let fresh = DataBase::open(dir.path().join("fresh.db")).await.unwrap();
+        assert!(fresh.import_encrypted_bytes(&corrupt, &pass).await.is_err());
+        assert!(!fresh.is_initialized().await.unwrap());
+        assert!(!fresh.is_unlocked().await);
+    }
+
+    #[tokio::test]
+    async fn scheduler_records_backup_errors() {
+        let (dir, db) = unlocked_db().await;
+        assert_eq!(db.backup_status().await.unwrap(), None);
+
+        let backups = dir.path().join("backups");
+        let schedule = BackupSchedule {
+            dir: backups.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        db.set_backup_schedule(schedule, &SecretString::new("auto-pass".into()))
+            .await
+            .unwrap();
+        // на месте каталога оказался файл — бэкап не записать
+        std::fs::remove_dir(&backups).unwrap();
+        std::fs::write(&backups, b"").unwrap();
+
+        db.backup_tick().await;
+        let status = db.backup_status().await.unwrap().unwrap();
+        assert_eq!(status.last_run_at, None);
+        assert!(status.last_error.is_some());
+        assert!(status.last_error_at.is_some());
+
+        std::fs::remove_file(&backups).unwrap();
+        db.backup_tick().await;
+        let status = db.backup_status().await.unwrap().unwrap();
+        assert!(status.last_run_at.is_some());
+        assert_eq!((status.last_error, status.last_error_at), (None, None));
+    }
+
+    #[tokio::test]
+    async fn scheduled_backups_rotate_and_restore() {
+        let (dir, db) = unlocked_db().await;
+        assert!(!db.backup_due().await.unwrap());
+
+        let schedule = BackupSchedule {
+            dir: dir.path().join("backups").to_string_lossy().into_owned(),
+            interval_secs: None,
+            after_changes: Some(2),
+            keep_daily: 7,
+            keep_weekly: 4,
+        };
+        db.set_backup_schedule(schedule, &SecretString::new("auto-pass".into()))
+            .await
+            .unwrap();
+
+        db.add_entry("a.com", "alice", "pw1", None).await.unwrap();
+        assert!(!db.backup_due().await.unwrap());
+        db.add_entry("a.com", "alice", "pw2", None).await.unwrap();
+        assert!(db.backup_due().await.unwrap());
+
+        let stored = db.run_scheduled_backup().await.unwrap();
+        assert!(!db.backup_due().await.unwrap());
+        assert_eq!(db.list_stored_backups().await.unwrap(), vec![stored.clone()]);
+        let report = db.verify_stored_backup(&stored.file_name).await.unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.entry_count, 2);
+
+        // файл самодостаточный: открывается паролем бэкапа без хранилища
+        let path = dir.path().join("backups").join(&stored.file_name);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let bytes = std::fs::read(path).unwrap();
+        assert!(backup::open(&SecretString::new("auto-pass".into()), &bytes).is_ok());
+
+        db.add_entry("c.com", "carol", "pw3", None).await.unwrap();
+        let summary = db.restore_stored_backup(&stored.file_name).await.unwrap();
+        assert_eq!(summary.inserted, 2);
+        // обе записи с одинаковой парой site/username на месте
+        let mut passwords = Vec::new();
+        for it in db.list_entries(None, false).await.unwrap() {
+            passwords.push(db.get_entry(it.id).await.unwrap().password);
+        }
+        passwords.sort();
+        assert_eq!(passwords, ["pw1", "pw2"]);
+        assert!(db.verify_stored_backup("../t.db").await.is_err());
+
+        // второй бэкап в ту же секунду не затирает первый, а ротация оставляет
+        // за день именно более новый
+        let first = db.run_scheduled_backup().await.unwrap();
+        let second = db.run_scheduled_backup().await.unwrap();
+        assert_ne!(first.file_name, second.file_name);
+        assert_eq!(db.list_stored_backups().await.unwrap(), vec![second.clone()]);
+        assert!(db.verify_stored_backup(&second.file_name).await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn verify_backup_reports_without_importing() {
+        let (dir, db) = unlocked_db().await;
+        db.add_entry("a.com", "alice", "pw", None).await.unwrap();
+        let pass = SecretString::new("backup-pass".into());
+
+        let path = dir.path().join("b.vault");
+        db.export_encrypted_backup(&path, &pass).await.unwrap();
+        let report = db.verify_backup(&path, Some(&pass)).await.unwrap();
+        assert_eq!(report.format_version, backup::FORMAT_VERSION);
+        assert_eq!(report.entry_count, 1);
+        assert!(report.created_at.is_some());
+        assert!(report.is_ok(), "{:?}", report.problems);
+
+        let wrong = SecretString::new("nope".into());
+        assert!(matches!(
+            db.verify_backup(&path, Some(&wrong)).await,
+            Err(VaultError::BadMasterPassword)
+        ));
+
+        let broken = dir.path().join("broken.vault");
+        let sealed = sealed_items(
+            &pass,
+            serde_json::json!([
+                { "site": "", "username": "x", "password": "pw", "notes": null },
+                { "site": "b.com", "username": "bob", "password": "pw", "notes": null },
+                { "site": "b.com", "username": "bob", "password": "pw", "notes": null },
+                { "site": "c.com" },
+            ]),
+        );
+        std::fs::write(&broken, sealed).unwrap();
+        let report = db.verify_backup(&broken, Some(&pass)).await.unwrap();
+        assert_eq!(report.entry_count, 4);
+        // одинаковые пары site/username — нормальные данные, а не повреждение
+        assert_eq!(report.problems.len(), 2, "{:?}", report.problems);
+
+        assert_eq!(db.list_entries(None, false).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn plaintext_export_requires_master() {
+        let (dir, db) = unlocked_db().await;
+        db.add_entry("a.com", "alice", "p,w\"1", Some("line1\nline2"))
+            .await
+            .unwrap();
+
+        let path = dir.path().join("out.csv");
+        let wrong = SecretString::new("nope".into());
+        assert!(matches!(
+            db.export_plaintext(&path, &wrong, ExportFormat::Csv).await,
+            Err(VaultError::BadMasterPassword)
+        ));
+        assert!(!path.exists());
+
+        let master = SecretString::new("master123".into());
+        let n = db
+            .export_plaintext(&path, &master, ExportFormat::Csv)
+            .await
+            .unwrap();
+        assert_eq!(n, 1);
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert!(csv.starts_with("site,username,password,notes,created_at,updated_at\r\n"));
+        assert!(csv.contains("a.com,alice,\"p,w\"\"1\",\"line1\nline2\","));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let json_path = dir.path().join("out.json");
+        db.export_plaintext(&json_path, &master, ExportFormat::Json)
+            .await
+            .unwrap();
+        let parsed: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&json_path).unwrap()).unwrap();
+        assert_eq!(parsed[0]["password"], "p,w\"1");
+    }
+
+    #[tokio::test]
+    async fn entries_are_sorted_by_weakness() {
+        let (_dir, db) = unlocked_db().await;
+        let strong = db
+            .add_entry("a.com", "alice", "k9#Vq!2xLm@8Zr", None)
+            .await
+            .unwrap();
+        let weak = db.add_entry("b.com", "bob", "123456", None).await.unwrap();
+
+        let list = db.list_entries(None, true).await.unwrap();
+        assert_eq!(list[0].id, weak);
+        assert_eq!(list[0].strength, Some(0));
+        assert_eq!(list[1].id, strong);
+        assert_eq!(list[1].strength, Some(4));
+
+        db.update_entry(weak, 1, "b.com", "bob", Some("tiger-plank-osmosis-vivid"), None)
+            .await
+            .unwrap();
+        let list = db.list_entries(None, true).await.unwrap();
+        assert!(list.iter().all(|e| e.strength.unwrap() >= 3));
+
+        // записи без оценки (из старых версий) досчитываются при разблокировке
+        sqlx::query("UPDATE entries SET strength = NULL")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        db.lock().await;
+        db.unlock(SecretString::new("master123".into()))
+            .await
+            .unwrap();
+        let list = db.list_entries(None, false).await.unwrap();
+        assert!(list.iter().all(|e| e.strength.is_some()));
+
+        // битая запись не мешает разблокировке и досчёту остальных
+        sqlx::query("UPDATE entries SET strength = NULL")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE entries SET password_enc = x'00' WHERE id = ?")
+            .bind(strong)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        db.lock().await;
+        db.unlock(SecretString::new("master123".into()))
+            .await
+            .unwrap();
+        assert!(db.is_unlocked().await);
+        let list = db.list_entries(None, false).await.unwrap();
+        let strength = |id| list.iter().find(|e| e.id == id).unwrap().strength;
+        assert_eq!((strength(weak).is_some(), strength(strong)), (true, None));
+    }
+
+    #[tokio::test]
+    async fn audit_reports_reused_weak_stale_and_duplicates() {
+        let (_dir, db) = unlocked_db().await;
+        let a = db.add_entry("a.com", "alice", "k9#Vq!2xLm@8Zr", None).await.unwrap();
+        let b = db.add_entry("b.com", "bob", "k9#Vq!2xLm@8Zr", None).await.unwrap();
+        let c = db.add_entry("c.com", "", "123456", None).await.unwrap();
+        let d = db.add_entry("A.com", "Alice", "Zr8@mLx2!qV#9k", None).await.unwrap();
+        sqlx::query("UPDATE entries SET updated_at = ? WHERE id = ?")
+            .bind(epoch() - 400 * 24 * 60 * 60)
+            .bind(b)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let report = db.vault_audit(&AuditOptions::default()).await.unwrap();
+        assert_eq!(report.total, 4);
+        let ids = |g: &[AuditEntryRef]| g.iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(report.reused.len(), 1);
+        assert_eq!(ids(&report.reused[0]), vec![a, b]);
+        assert_eq!(report.weak.len(), 1);
+        assert_eq!(report.weak[0].entry.id, c);
+        assert_eq!(report.stale.len(), 1);
+        assert_eq!(report.stale[0].entry.id, b);
+        assert!(report.stale[0].age_days >= 400);
+        assert_eq!(ids(&report.missing_username), vec![c]);
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(ids(&report.duplicates[0]), vec![a, d]);
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("k9#Vq"));
+    }
+
+    #[tokio::test]
+    async fn generator_honours_profiles() {
+        let (_dir, db) = unlocked_db().await;
+
+        let corp = db
+            .save_profile(GeneratorProfile {
+                name: "corp".into(),
+                min_length: 10,
+                max_length: 16,
+                symbol_set: Some("#".into()),
+                must_match: vec!["^[A-Za-z]".into()],
+                site_patterns: vec!["*.corp.example".into()],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let pin = db
+            .save_profile(GeneratorProfile {
+                name: "pin".into(),
+                min_length: 6,
+                max_length: 6,
+                lower: false,
+                upper: false,
+                symbols: false,
+                site_patterns: vec!["bank.example".into()],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(db.list_profiles().await.unwrap().len(), 2);
+
+        // режим из запроса не теряется: PIN по профилю банка — шесть цифр
+        let pin_opts = GeneratorOptions {
+            mode: generator::GeneratorMode::Pin,
+            length: 4,
+            ..Default::default()
+        };
+        let g = db
+            .generate_password(&pin_opts, None, Some("bank.example"))
+            .await
+            .unwrap();
+        assert!(g.password.len() == 6 && g.password.chars().all(|c| c.is_ascii_digit()));
+        // а профилю, где обязательны буквы, PIN не подходит
+        assert!(db
+            .generate_password(&pin_opts, None, Some("git.corp.example"))
+            .await
+            .is_err());
+
+        let opts = GeneratorOptions::default();
+        let g = db
+            .generate_password(&opts, None, Some("git.corp.example"))
+            .await
+            .unwrap();
+        assert_eq!(g.password.len(), 16);
+        assert!(g.password.starts_with(|c: char| c.is_ascii_alphabetic()));
+        assert!(g.password.chars().all(|c| c.is_ascii_alphanumeric() || c == '#'));
+
+        let free = db.generate_password(&opts, None, Some("example.org")).await.unwrap();
+        assert_eq!(free.password.len(), 20);
+
+        // профиль записи важнее шаблона сайта
+        let id = db
+            .add_entry("vpn.corp.example", "alice", "x", None)
+            .await
+            .unwrap();
+        let g = db.generate_password(&opts, Some(id), None).await.unwrap();
+        assert_eq!(g.password.len(), 16);
+        db.set_entry_profile(id, Some(pin)).await.unwrap();
+        let g = db.generate_password(&opts, Some(id), None).await.unwrap();
+        assert!(g.password.len() == 6 && g.password.chars().all(|c| c.is_ascii_digit()));
+
+        db.delete_profile(pin).await.unwrap();
+        let g = db.generate_password(&opts, Some(id), None).await.unwrap();
+        assert_eq!(g.password.len(), 16);
+        assert!(db.set_entry_profile(id, Some(pin)).await.is_err());
+        assert!(db
+            .save_profile(GeneratorProfile {
+                id: corp,
+                name: "corp".into(),
+                min_length: 20,
+                max_length: 10,
+                ..Default::default()
+            })
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn rotation_reminders_follow_password_changes() {
+        let (dir, db) = unlocked_db().await;
+        let vpn = db.add_entry("vpn", "alice", "pw1", None).await.unwrap();
+        let mail = db.add_entry("mail", "alice", "pw2", None).await.unwrap();
+        db.add_entry("wiki", "alice", "pw3", None).await.unwrap();
+        db.set_entry_rotation(vpn, Some(90)).await.unwrap();
+        db.set_entry_rotation(mail, Some(30)).await.unwrap();
+        assert!(db.set_entry_rotation(mail, Some(0)).await.is_err());
+        // интервал попадает в бэкап, так что это изменение для автобэкапа
+        assert_eq!(db.changes.load(Ordering::Relaxed), 5);
+
+        let day = 24 * 60 * 60;
+        sqlx::query("UPDATE entries SET password_changed_at = ? WHERE id = ?")
+            .bind(epoch() - 100 * day)
+            .bind(vpn)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let due = db.rotation_due(0).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].entry.id, vpn);
+        assert!(due[0].expired);
+        assert_eq!(due[0].days_left, -10);
+        assert_eq!(db.rotation_due(30).await.unwrap().len(), 2);
+
+        // смена заметок не сбрасывает срок, смена пароля — сбрасывает
+        db.update_entry(vpn, 1, "vpn", "alice", None, Some("n"))
+            .await
+            .unwrap();
+        assert_eq!(db.rotation_due(0).await.unwrap().len(), 1);
+        db.update_entry(vpn, 2, "vpn", "alice", Some("pw4"), None)
+            .await
+            .unwrap();
+        assert!(db.rotation_due(0).await.unwrap().is_empty());
+
+        // интервал переживает бэкап
+        let pass = SecretString::new("backup-pass".into());
+        let data = db.export_encrypted_bytes(&pass).await.unwrap();
+        let db2 = DataBase::open(dir.path().join("t2.db")).await.unwrap();
+        db2.init_master(SecretString::new("master123".into()))
+            .await
+            .unwrap();
+        db2.import_encrypted_bytes(&data, &pass).await.unwrap();
+        assert_eq!(db2.rotation_due(90).await.unwrap().len(), 2);
+
+        db.lock().await;
+        assert!(matches!(db.rotation_due(0).await, Err(VaultError::Locked)));
+    }
+
+    /// Базы всех прежних схем: с номером версии и без него (созданные до миграций).
+    /// В каждой — мастер-конфиг и запись в столбцах, которые тогда существовали.
+    #[tokio::test]
+    async fn opens_databases_from_every_prior_schema() {
+        let dir = tempdir().unwrap();
+        let master = SecretString::new("master123".into());
+        for layout in 1..=migrations::SCHEMA_VERSION {
+            for versioned in [true, false] {
+                if versioned && layout == migrations::SCHEMA_VERSION
+                    || !versioned && layout > migrations::UNVERSIONED_LAYOUTS
+                {
+                    continue;
+                }
+                let path = dir.path().join(format!("v{layout}-{versioned}.db"));
+                let opts = SqliteConnectOptions::from_str(&format!("sqlite://{}", path.display()))
+                    .unwrap()
+                    .create_if_missing(true);
+                let pool = SqlitePoolOptions::new().connect_with(opts).await.unwrap();
+                migrations::migrate_to(&pool, layout).await.unwrap();
+                let mut conn = pool.acquire().await.unwrap();
+                if !versioned {
+                    conn.execute("PRAGMA user_version = 0").await.unwrap();
+                }
+                let key = write_master_config(&mut conn, &master).await.unwrap();
+                sqlx::query(
+                    "INSERT INTO entries(site, username, password_enc, notes_enc, created_at, updated_at)
+                     VALUES('example.com', 'alice', ?, NULL, 100, 200)",
+                )
+                .bind(encrypt(&key, b"password").unwrap())
+                .execute(&mut *conn)
+                .await
+                .unwrap();
+                if layout >= 5 {
+                    // с этой схемы `add_entry` сам заполняет дату смены пароля
+                    conn.execute("UPDATE entries SET password_changed_at = updated_at")
+                        .await
+                        .unwrap();
+                }
+                conn.close().await.unwrap();
+                pool.close().await;
+
+                let db = DataBase::open(&path).await.unwrap();
+                let mut conn = db.pool.acquire().await.unwrap();
+                assert_eq!(
+                    migrations::schema_version(&mut conn).await.unwrap(),
+                    migrations::SCHEMA_VERSION,
+                    "layout {layout}, versioned {versioned}"
+                );
+                drop(conn);
+                db.unlock(master.clone()).await.unwrap();
+                let e = db.get_entry(1).await.unwrap();
+                assert_eq!((e.password.as_str(), e.notes), ("password", None));
+                let items = db.list_entries(None, false).await.unwrap();
+                assert!(items[0].strength.is_some());
+                // срок ротации отсчитывается от последнего изменения записи
+                db.set_entry_rotation(1, Some(1)).await.unwrap();
+                let due = db.rotation_due(0).await.unwrap();
+                assert_eq!(due[0].changed_at, 200);
+            }
+        }
+    }
+
+    #[test]
+    fn errors_serialise_with_stable_codes() {
+        let v = serde_json::to_value(VaultError::UnsupportedSchema {
+            found: 9,
+            supported: 5,
+        })
+        .unwrap();
+        assert_eq!(v["code"], "unsupported_schema");
+        assert_eq!(v["details"]["found"], 9);
+        assert!(v["message"].as_str().unwrap().contains('9'));
+
+        let v = serde_json::to_value(VaultError::Locked).unwrap();
+        assert_eq!(v["code"], "locked");
+        assert_eq!(v["details"], serde_json::Value::Null);
+
+        let v = serde_json::to_value(VaultError::InvalidDataset("not sorted".into())).unwrap();
+        assert_eq!(v["code"], "invalid_dataset");
+        assert!(v["message"].as_str().unwrap().contains("not sorted"));
+    }
+
+    #[tokio::test]
+    async fn missing_entries_are_reported_as_not_found() {
+        let (_dir, db) = unlocked_db().await;
+        let id = db.add_entry("example.com", "alice", "pw", None).await.unwrap();
+        let missing = id + 1;
+
+        let not_found = |r: ResultT<()>| matches!(r, Err(VaultError::NotFound { id }) if id == missing);
+        assert!(not_found(db.get_entry(missing).await.map(drop)));
+        assert!(not_found(db.get_password(missing).await.map(drop)));
+        assert!(not_found(
+            db.update_entry(missing, 1, "x", "y", Some("pw"), None)
+                .await
+                .map(drop)
+        ));
+        assert!(not_found(db.set_entry_rotation(missing, Some(30)).await));
+        assert!(not_found(db.set_entry_profile(missing, None).await));
+        let opts = GeneratorOptions::default();
+        assert!(not_found(
+            db.generate_password(&opts, Some(missing), None).await.map(drop)
+        ));
+
+        // удаление сообщает, была ли запись
+        db.delete_entry(id).await.unwrap();
+        assert!(matches!(
+            db.delete_entry(id).await,
+            Err(VaultError::NotFound { id: gone }) if gone == id
+        ));
+        assert!(db.list_entries(None, false).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn concurrent_edits_conflict() {
+        let (_dir, db) = unlocked_db().await;
+        let id = db.add_entry("example.com", "alice", "pw", None).await.unwrap();
+
+        // оба окна открыли запись на ревизии 1
+        let seen = db.get_entry(id).await.unwrap().revision;
+        assert_eq!(seen, 1);
+        let rev = db
+            .update_entry(id, seen, "example.com", "alice", Some("first"), None)
+            .await
+            .unwrap();
+        assert_eq!(rev, 2);
+        match db
+            .update_entry(id, seen, "example.com", "alice", Some("second"), None)
+            .await
+        {
+            Err(VaultError::Conflict { id: cid, revision }) => assert_eq!((cid, revision), (id, 2)),
+            other => panic!("expected Conflict, got {other:?}"),
+        }
+        assert_eq!(db.get_entry(id).await.unwrap().password, "first");
+
+        // с актуальной ревизией правка проходит
+        db.update_entry(id, rev, "example.com", "alice", Some("second"), None)
+            .await
+            .unwrap();
+        let e = db.get_entry(id).await.unwrap();
+        assert_eq!((e.password.as_str(), e.revision), ("second", 3));
+        assert_eq!(db.list_entries(None, false).await.unwrap()[0].revision, 3);
+    }
+
+    #[tokio::test]
+    async fn search_ranks_and_pages() {
+        let (_dir, db) = unlocked_db().await;
+        let host = db
+            .add_entry("https://github.com/login", "alice", "pw1", None)
+            .await
+            .unwrap();
+        let prefix = db.add_entry("github-enterprise.corp", "alice", "pw2", None).await.unwrap();
+        let noted = db
+            .add_entry("corp.example", "bob", "pw3", Some("Backup codes for GitHub"))
+            .await
+            .unwrap();
+        db.add_entry("gitlab.com", "carol", "pw4", None).await.unwrap();
+
+        let search = |query: &str, in_notes: bool| {
+            let opts = SearchOptions {
+                query: query.into(),
+                in_notes,
+                ..Default::default()
+            };
+            let db = db.clone();
+            async move { db.search_entries(&opts).await.unwrap() }
+        };
+        let ids = |r: &SearchResults| r.hits.iter().map(|h| h.entry.id).collect::<Vec<_>>();
+
+        let r = search("github.com", false).await;
+        assert_eq!(r.hits[0].entry.id, host);
+        assert_eq!(r.hits[0].kind, search::MatchKind::Host);
+
+        let r = search("githb", false).await;
+        assert_eq!(ids(&r), vec![prefix, host]);
+        assert!(r.hits.iter().all(|h| h.kind == search::MatchKind::Fuzzy));
+
+        // заметки — только по запросу
+        assert_eq!(search("backup", false).await.total, 0);
+        let r = search("backup", true).await;
+        assert_eq!(ids(&r), vec![noted]);
+
+        // битая заметка не ломает поиск — запись находится по сайту
+        let broken = db
+            .add_entry("broken.example", "dave", "pw5", Some("backup"))
+            .await
+            .unwrap();
+        sqlx::query("UPDATE entries SET notes_enc = x'00' WHERE id = ?")
+            .bind(broken)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(ids(&search("backup", true).await), vec![noted]);
+        assert_eq!(ids(&search("broken", true).await), vec![broken]);
+
+        let r = db
+            .search_entries(&SearchOptions {
+                query: "git".into(),
+                offset: 1,
+                limit: 1,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!((r.total, r.hits.len()), (3, 1));
+
+        db.lock().await;
+        assert!(search("git", false).await.total > 0);
+        let locked = db
+            .search_entries(&SearchOptions {
+                query: "git".into(),
+                in_notes: true,
+                ..Default::default()
+            })
+            .await;
+        assert!(matches!(locked, Err(VaultError::Locked)));
+    }
+
+    #[tokio::test]
+    async fn query_filters_sorts_and_pages() {
+        let (_dir, db) = unlocked_db().await;
+        let mut ids = Vec::new();
+        for (i, site) in ["delta.io", "Alpha.com", "charlie.net", "bravo.org"].iter().enumerate() {
+            let notes = (i % 2 == 0).then_some("note");
+            let id = db.add_entry(site, "user", "pw", notes).await.unwrap();
+            sqlx::query("UPDATE entries SET created_at = ?, updated_at = ? WHERE id = ?")
+                .bind(1000 * (i as i64 + 1))
+                .bind(5000 - 1000 * i as i64)
+                .bind(id)
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        let sites = |p: &EntryPage| p.items.iter().map(|i| i.site.clone()).collect::<Vec<_>>();
+
+        let by_site = ListQuery {
+            sort: SortKey::Site,
+            descending: false,
+            ..Default::default()
+        };
+        let all = db.query_entries(&by_site).await.unwrap();
+        assert_eq!(sites(&all), ["Alpha.com", "bravo.org", "charlie.net", "delta.io"]);
+
+        // страницы не пересекаются, total — без учёта страницы
+        let page = db
+            .query_entries(&ListQuery {
+                offset: 1,
+                limit: Some(2),
+                ..by_site.clone()
+            })
+            .await
+            .unwrap();
+        assert_eq!((page.total, sites(&page)), (4, vec!["bravo.org".to_owned(), "charlie.net".to_owned()]));
+
+        let filtered = db
+            .query_entries(&ListQuery {
+                created_from: Some(2000),
+                created_to: Some(4000),
+                has_notes: Some(true),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!((filtered.total, sites(&filtered)), (1, vec!["charlie.net".to_owned()]));
+
+        let recent = db
+            .query_entries(&ListQuery {
+                updated_from: Some(4000),
+                search: Some("a".into()),
+                sort: SortKey::Created,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(sites(&recent), ["Alpha.com", "delta.io"]);
+        assert_eq!(db.list_entries(None, false).await.unwrap()[0].id, ids[0]);
+    }
+
+    #[tokio::test]
+    async fn reveals_are_counted_as_uses() {
+        let (_dir, db) = unlocked_db().await;
+        let mail = db.add_entry("mail.com", "alice", "pw1", None).await.unwrap();
+        let bank = db.add_entry("bank.com", "alice", "pw2", None).await.unwrap();
+        let forum = db.add_entry("forum.net", "alice", "pw3", None).await.unwrap();
+
+        db.get_entry(mail).await.unwrap();
+        db.get_password(mail).await.unwrap();
+        db.get_entry(bank).await.unwrap();
+        // логин и сайт — не секрет, их копирование использованием не считается
+        db.entry_field(forum, EntryField::Username).await.unwrap();
+        db.entry_field(forum, EntryField::Site).await.unwrap();
+        // bank использован позже mail
+        sqlx::query("UPDATE entries SET last_used_at = last_used_at - 60 WHERE id = ?")
+            .bind(mail)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let order = |sort| {
+            let db = db.clone();
+            async move {
+                let q = ListQuery {
+                    sort,
+                    ..Default::default()
+                };
+                let page = db.query_entries(&q).await.unwrap();
+                page.items.iter().map(|i| (i.id, i.use_count)).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(order(SortKey::LastUsed).await, [(bank, 1), (mail, 2), (forum, 0)]);
+        assert_eq!(order(SortKey::UseCount).await, [(mail, 2), (bank, 1), (forum, 0)]);
+
+        // использование — не правка
+        let e = db.get_entry(forum).await.unwrap();
+        assert_eq!(e.revision, 1);
+        assert_eq!(e.updated_at, e.created_at);
+        assert!(matches!(
+            db.record_use(forum + 100).await,
+            Err(VaultError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn entry_view_has_no_secrets() {
+        let (_dir, db) = unlocked_db().await;
+        let id = db
+            .add_entry("mail.com", "alice", "pw1", Some("pin 1234"))
+            .await
+            .unwrap();
+        let bare = db.add_entry("bank.com", "bob", "pw2", None).await.unwrap();
+
+        let v = db.entry_view(id).await.unwrap();
+        assert_eq!((v.meta.site.as_str(), v.meta.username.as_str()), ("mail.com", "alice"));
+        assert!(v.has_notes);
+        assert!(!db.entry_view(bare).await.unwrap().has_notes);
+        let json = serde_json::to_value(&v).unwrap();
+        assert!(json.get("password").is_none() && json.get("notes").is_none());
+        assert_eq!(json["has_notes"], true);
+        assert_eq!(json["site"], "mail.com");
+
+        // просмотр — не использование, а раскрытие поля — использование
+        assert_eq!(db.entry_view(id).await.unwrap().meta.use_count, 0);
+        assert_eq!(
+            db.entry_field(id, EntryField::Notes).await.unwrap().as_str(),
+            "pin 1234"
+        );
+        assert_eq!(db.entry_view(id).await.unwrap().meta.use_count, 1);
+
+        assert!(matches!(
+            db.entry_view(id + 100).await,
+            Err(VaultError::NotFound { .. })
+        ));
+        db.lock().await;
+        assert!(matches!(db.entry_view(id).await, Err(VaultError::Locked)));
+    }
+
+    #[tokio::test]
+    async fn pinned_and_favourite_entries_come_first() {
+        let (_dir, db) = unlocked_db().await;
+        let mut ids = Vec::new();
+        for site in ["a.com", "b.com", "c.com", "d.com", "e.com"] {
+            ids.push(db.add_entry(site, "alice", "pw", None).await.unwrap());
+        }
+        let [a, b, c, d, e] = ids[..] else { unreachable!() };
+
+        db.set_favorite(b, true).await.unwrap();
+        db.set_pins(&[d, a]).await.unwrap();
+        let q = ListQuery {
+            sort: SortKey::Site,
+            descending: false,
+            favorites_first: true,
+            ..Default::default()
+        };
+        let order = |q: ListQuery| {
+            let db = db.clone();
+            async move {
+                let page = db.query_entries(&q).await.unwrap();
+                page.items.iter().map(|i| i.id).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(order(q.clone()).await, [d, a, b, c, e]);
+        let items = db.query_entries(&q).await.unwrap().items;
+        assert!(items[1].favorite && items[1].pin_order == Some(1));
+
+        // перестановка, открепление через избранное, ошибка без частичных изменений
+        db.set_pins(&[a, d]).await.unwrap();
+        assert_eq!(order(q.clone()).await, [a, d, b, c, e]);
+        db.set_favorite(a, false).await.unwrap();
+        assert_eq!(order(q.clone()).await, [d, b, a, c, e]);
+        assert!(matches!(
+            db.set_pins(&[c, e + 100]).await,
+            Err(VaultError::NotFound { .. })
+        ));
+        assert_eq!(order(q.clone()).await, [d, b, a, c, e]);
+
+        // без favorites_first — обычная сортировка
+        let plain = ListQuery {
+            favorites_first: false,
+            ..q
+        };
+        assert_eq!(order(plain).await, [a, b, c, d, e]);
+    }
+}
@@ -0,0 +1,59 @@
+use std::{io::Write, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::backup::BackupEntry;
+use super::db::{ResultT, VaultError};
+
+/// Формат незашифрованного экспорта.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// CSV по RFC 4180: разделитель `,`, строки через CRLF, поля с `,` `"` и переводами строк в кавычках.
+pub(crate) fn to_csv(items: &[BackupEntry]) -> String {
+    let mut out = String::from("site,username,password,notes,created_at,updated_at\r\n");
+    for it in items {
+        let fields = [
+            csv_field(&it.site),
+            csv_field(&it.username),
+            csv_field(&it.password),
+            csv_field(it.notes.as_deref().unwrap_or("")),
+            it.created_at.to_string(),
+            it.updated_at.to_string(),
+        ];
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Пишет файл с правами только для владельца (0600 на unix). Существующий файл
+/// сначала сужается в правах, потом перезаписывается.
+pub(crate) fn write_private(path: &Path, bytes: &[u8]) -> ResultT<()> {
+    let io = |e: std::io::Error| VaultError::Other(e.to_string());
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        opts.mode(0o600);
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).map_err(io)?;
+        }
+    }
+    let mut f = opts.open(path).map_err(io)?;
+    f.write_all(bytes).map_err(io)?;
+    f.sync_all().map_err(io)
+}
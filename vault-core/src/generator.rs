@@ -0,0 +1,493 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::db::{ResultT, VaultError};
+
+const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()-_=+[]{};:,.?/\\|`~";
+/// Символы, которые легко спутать при чтении или наборе.
+const AMBIGUOUS: &str = "Il1|O0o`";
+
+pub const MAX_LENGTH: usize = 256;
+/// Если в среднем нужно больше попыток, ограничения считаются невыполнимыми для длины.
+const MAX_EXPECTED_ATTEMPTS: f64 = 10_000.0;
+
+/// Согласные и гласные слогового режима: без `c`, `q`, `w`, `x`, `y`,
+/// которые по-разному читаются и путаются на слух.
+const CONSONANTS: &str = "bdfghjklmnprstvz";
+const VOWELS: &str = "aeiou";
+const MIN_PIN: usize = 4;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratorMode {
+    /// Случайные символы из включённых классов.
+    #[default]
+    Random,
+    /// Только цифры, без повторов вида `111` и последовательностей вида `123`/`987`.
+    Pin,
+    /// Чередование согласных и гласных (`tavokime…`), легко набрать с пульта.
+    /// `upper` делает первую букву заглавной, `digits` дописывает цифру в конец.
+    Pronounceable,
+}
+
+/// Параметры генератора. Включённый класс символов всегда встречается хотя бы раз.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GeneratorOptions {
+    pub mode: GeneratorMode,
+    pub length: usize,
+    pub lower: bool,
+    pub upper: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    pub min_lower: usize,
+    pub min_upper: usize,
+    pub min_digits: usize,
+    pub min_symbols: usize,
+    /// Допустимые спецсимволы вместо стандартного набора.
+    pub symbol_set: Option<String>,
+    /// Символы, которые нельзя использовать.
+    pub exclude: String,
+    /// Исключить похожие символы (`I`, `l`, `1`, `O`, `0` …).
+    pub avoid_ambiguous: bool,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            mode: GeneratorMode::Random,
+            length: 20,
+            lower: true,
+            upper: true,
+            digits: true,
+            symbols: true,
+            min_lower: 0,
+            min_upper: 0,
+            min_digits: 0,
+            min_symbols: 0,
+            symbol_set: None,
+            exclude: String::new(),
+            avoid_ambiguous: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GeneratedPassword {
+    pub password: String,
+    /// log2 числа паролей, которые генератор мог выдать с равной вероятностью.
+    pub entropy_bits: f64,
+}
+
+/// Равномерный индекс в `0..n` отбраковкой: значения из неполного хвоста диапазона
+/// u64 отбрасываются, поэтому остаток от деления не смещён.
+pub fn uniform<R: RngCore + ?Sized>(rng: &mut R, n: usize) -> usize {
+    debug_assert!(n > 0);
+    let n = n as u64;
+    let limit = u64::MAX - u64::MAX % n;
+    loop {
+        let r = rng.next_u64();
+        if r < limit {
+            return (r % n) as usize;
+        }
+    }
+}
+
+struct Class {
+    chars: Vec<char>,
+    min: usize,
+}
+
+fn classes(opts: &GeneratorOptions) -> ResultT<Vec<Class>> {
+    let allowed =
+        |c: &char| !(opts.exclude.contains(*c) || opts.avoid_ambiguous && AMBIGUOUS.contains(*c));
+    let symbols = opts.symbol_set.as_deref().unwrap_or(SYMBOLS);
+    if symbols
+        .chars()
+        .any(|c| c.is_alphanumeric() || c.is_whitespace())
+    {
+        return Err(VaultError::Other(
+            "symbol set may not contain letters, digits or spaces".into(),
+        ));
+    }
+    let mut out = Vec::new();
+    for (on, set, min, name) in [
+        (opts.lower, LOWER, opts.min_lower, "lowercase letters"),
+        (opts.upper, UPPER, opts.min_upper, "uppercase letters"),
+        (opts.digits, DIGITS, opts.min_digits, "digits"),
+        (opts.symbols, symbols, opts.min_symbols, "symbols"),
+    ] {
+        if !on {
+            continue;
+        }
+        let mut chars: Vec<char> = set.chars().filter(allowed).collect();
+        // повторы в пользовательском наборе сместили бы распределение
+        chars.sort_unstable();
+        chars.dedup();
+        if chars.is_empty() {
+            return Err(VaultError::Other(format!("all {name} are excluded")));
+        }
+        out.push(Class {
+            chars,
+            min: min.max(1),
+        });
+    }
+    if out.is_empty() {
+        return Err(VaultError::Other("no character classes enabled".into()));
+    }
+    Ok(out)
+}
+
+/// Вероятность, что равномерная строка длины `len` над объединением классов
+/// содержит не меньше `min` символов каждого класса.
+fn acceptance(classes: &[Class], len: usize) -> f64 {
+    let total: usize = classes.iter().map(|c| c.chars.len()).sum();
+    // binom[n][k]
+    let mut binom = vec![vec![0f64; len + 1]; len + 1];
+    for n in 0..=len {
+        binom[n][0] = 1.0;
+        for k in 1..=n {
+            binom[n][k] = binom[n - 1][k - 1] + if k < n { binom[n - 1][k] } else { 0.0 };
+        }
+    }
+    // p[n]: вероятность, что n позиций, отданных уже разобранным классам, удовлетворяют их минимумам
+    let mut p = vec![0f64; len + 1];
+    p[0] = 1.0;
+    for class in classes {
+        let q = class.chars.len() as f64 / total as f64;
+        let mut next = vec![0f64; len + 1];
+        for (n, slot) in next.iter_mut().enumerate() {
+            for c in class.min..=n {
+                *slot += binom[n][c] * q.powi(c as i32) * p[n - c];
+            }
+        }
+        p = next;
+    }
+    p[len]
+}
+
+/// Проверенные параметры генератора: алфавит и минимумы классов.
+pub(crate) struct Charset {
+    classes: Vec<Class>,
+    alphabet: Vec<(usize, char)>,
+    length: usize,
+    /// log2 числа строк, удовлетворяющих ограничениям.
+    pub(crate) entropy_bits: f64,
+}
+
+impl Charset {
+    pub(crate) fn new(opts: &GeneratorOptions) -> ResultT<Self> {
+        if opts.length == 0 || opts.length > MAX_LENGTH {
+            return Err(VaultError::Other(format!(
+                "password length must be between 1 and {MAX_LENGTH}"
+            )));
+        }
+        let classes = classes(opts)?;
+        let required: usize = classes.iter().map(|c| c.min).sum();
+        if required > opts.length {
+            return Err(VaultError::Other(format!(
+                "{required} required characters do not fit into length {}",
+                opts.length
+            )));
+        }
+        let accept = acceptance(&classes, opts.length);
+        if accept * MAX_EXPECTED_ATTEMPTS < 1.0 {
+            return Err(VaultError::Other(
+                "required character counts are too strict for this length".into(),
+            ));
+        }
+
+        let alphabet: Vec<(usize, char)> = classes
+            .iter()
+            .enumerate()
+            .flat_map(|(i, c)| c.chars.iter().map(move |&ch| (i, ch)))
+            .collect();
+        let entropy_bits = opts.length as f64 * (alphabet.len() as f64).log2() + accept.log2();
+        Ok(Self {
+            classes,
+            alphabet,
+            length: opts.length,
+            entropy_bits,
+        })
+    }
+
+    /// Пароль, равномерно распределённый среди всех строк нужной длины,
+    /// удовлетворяющих ограничениям: строка из объединения классов перегенерируется,
+    /// пока в ней не наберутся минимумы каждого класса.
+    pub(crate) fn sample<R: RngCore + ?Sized>(&self, rng: &mut R) -> String {
+        let mut counts = vec![0usize; self.classes.len()];
+        loop {
+            counts.iter_mut().for_each(|c| *c = 0);
+            let mut password = String::with_capacity(self.length);
+            for _ in 0..self.length {
+                let (class, ch) = self.alphabet[uniform(rng, self.alphabet.len())];
+                counts[class] += 1;
+                password.push(ch);
+            }
+            if self.classes.iter().zip(&counts).all(|(c, &n)| n >= c.min) {
+                return password;
+            }
+            zeroize::Zeroize::zeroize(&mut password);
+        }
+    }
+
+    /// Подходит ли готовый пароль под алфавит и минимумы классов.
+    pub(crate) fn admits(&self, password: &str) -> bool {
+        let mut counts = vec![0usize; self.classes.len()];
+        for ch in password.chars() {
+            match self.alphabet.iter().find(|&&(_, c)| c == ch) {
+                Some(&(class, _)) => counts[class] += 1,
+                None => return false,
+            }
+        }
+        self.classes.iter().zip(&counts).all(|(c, &n)| n >= c.min)
+    }
+}
+
+pub fn generate<R: RngCore + ?Sized>(
+    opts: &GeneratorOptions,
+    rng: &mut R,
+) -> ResultT<GeneratedPassword> {
+    if opts.length == 0 || opts.length > MAX_LENGTH {
+        return Err(VaultError::Other(format!(
+            "password length must be between 1 and {MAX_LENGTH}"
+        )));
+    }
+    match opts.mode {
+        GeneratorMode::Random => {
+            let charset = Charset::new(opts)?;
+            Ok(GeneratedPassword {
+                password: charset.sample(rng),
+                entropy_bits: charset.entropy_bits,
+            })
+        }
+        GeneratorMode::Pin => pin(opts.length, rng),
+        GeneratorMode::Pronounceable => pronounceable(opts, rng),
+    }
+}
+
+/// Три цифры подряд с шагом 0 или ±1: `777`, `345`, `654`.
+fn trivial_triple(a: u8, b: u8, c: u8) -> bool {
+    let (d1, d2) = (b as i8 - a as i8, c as i8 - b as i8);
+    d1 == d2 && d1.abs() <= 1
+}
+
+/// Число PIN-кодов длины `len` без тривиальных троек: динамика по двум последним цифрам.
+fn pin_count(len: usize) -> f64 {
+    let mut ways = [[1f64; 10]; 10];
+    for _ in 2..len {
+        let mut next = [[0f64; 10]; 10];
+        for a in 0..10u8 {
+            for b in 0..10u8 {
+                for c in 0..10u8 {
+                    if !trivial_triple(a, b, c) {
+                        next[b as usize][c as usize] += ways[a as usize][b as usize];
+                    }
+                }
+            }
+        }
+        ways = next;
+    }
+    ways.iter().flatten().sum()
+}
+
+/// PIN отбраковкой: равномерно среди кодов без тривиальных троек.
+fn pin<R: RngCore + ?Sized>(len: usize, rng: &mut R) -> ResultT<GeneratedPassword> {
+    if len < MIN_PIN {
+        return Err(VaultError::Other(format!(
+            "PIN must have at least {MIN_PIN} digits"
+        )));
+    }
+    let mut digits = vec![0u8; len];
+    loop {
+        digits.iter_mut().for_each(|d| *d = uniform(rng, 10) as u8);
+        if !digits.windows(3).any(|w| trivial_triple(w[0], w[1], w[2])) {
+            let password = digits.iter().map(|&d| char::from(b'0' + d)).collect();
+            zeroize::Zeroize::zeroize(&mut digits);
+            return Ok(GeneratedPassword {
+                password,
+                entropy_bits: pin_count(len).log2(),
+            });
+        }
+    }
+}
+
+fn pronounceable<R: RngCore + ?Sized>(
+    opts: &GeneratorOptions,
+    rng: &mut R,
+) -> ResultT<GeneratedPassword> {
+    let letters = opts.length - usize::from(opts.digits);
+    if letters < 2 {
+        return Err(VaultError::Other(
+            "pronounceable password must be at least 2 letters long".into(),
+        ));
+    }
+    let consonants: Vec<char> = CONSONANTS.chars().collect();
+    let vowels: Vec<char> = VOWELS.chars().collect();
+
+    let mut password = String::with_capacity(opts.length);
+    let mut entropy_bits = 0.0;
+    for i in 0..letters {
+        let set = if i % 2 == 0 { &consonants } else { &vowels };
+        let ch = set[uniform(rng, set.len())];
+        password.push(if i == 0 && opts.upper {
+            ch.to_ascii_uppercase()
+        } else {
+            ch
+        });
+        entropy_bits += (set.len() as f64).log2();
+    }
+    if opts.digits {
+        password.push(char::from(b'0' + uniform(rng, 10) as u8));
+        entropy_bits += 10f64.log2();
+    }
+    Ok(GeneratedPassword {
+        password,
+        entropy_bits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Выдаёт заданные значения по кругу.
+    struct Fixed(Vec<u64>, usize);
+
+    impl RngCore for Fixed {
+        fn next_u32(&mut self) -> u32 {
+            self.next_u64() as u32
+        }
+        fn next_u64(&mut self) -> u64 {
+            let v = self.0[self.1 % self.0.len()];
+            self.1 += 1;
+            v
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for b in dest {
+                *b = self.next_u64() as u8;
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_rejects_the_biased_tail() {
+        // для n = 3 значение u64::MAX попадает в неполный хвост и отбрасывается
+        let mut rng = Fixed(vec![u64::MAX, 4], 0);
+        assert_eq!(uniform(&mut rng, 3), 1);
+        assert_eq!(rng.1, 2);
+    }
+
+    #[test]
+    fn required_classes_and_minimums_are_met() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let opts = GeneratorOptions {
+            length: 12,
+            min_digits: 3,
+            min_symbols: 2,
+            exclude: "aeiou".into(),
+            avoid_ambiguous: true,
+            ..Default::default()
+        };
+        for _ in 0..200 {
+            let g = generate(&opts, &mut rng).unwrap();
+            let p = &g.password;
+            assert_eq!(p.chars().count(), 12);
+            assert!(p.chars().any(|c| c.is_ascii_lowercase()));
+            assert!(p.chars().any(|c| c.is_ascii_uppercase()));
+            assert!(p.chars().filter(|c| c.is_ascii_digit()).count() >= 3);
+            assert!(p.chars().filter(|c| SYMBOLS.contains(*c)).count() >= 2);
+            assert!(!p
+                .chars()
+                .any(|c| "aeiou".contains(c) || AMBIGUOUS.contains(c)));
+        }
+    }
+
+    #[test]
+    fn entropy_accounts_for_constraints() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let digits_only = GeneratorOptions {
+            length: 4,
+            lower: false,
+            upper: false,
+            symbols: false,
+            ..Default::default()
+        };
+        let g = generate(&digits_only, &mut rng).unwrap();
+        assert!((g.entropy_bits - 4.0 * 10f64.log2()).abs() < 1e-9);
+
+        // 2 символа, буква и цифра обязательны: 2 * 26 * 10 вариантов
+        let pair = GeneratorOptions {
+            length: 2,
+            upper: false,
+            symbols: false,
+            ..Default::default()
+        };
+        let g = generate(&pair, &mut rng).unwrap();
+        assert!((g.entropy_bits - 520f64.log2()).abs() < 1e-9);
+
+        let impossible = GeneratorOptions {
+            length: 3,
+            min_digits: 3,
+            ..Default::default()
+        };
+        assert!(generate(&impossible, &mut rng).is_err());
+    }
+
+    #[test]
+    fn pin_mode_skips_trivial_codes() {
+        // первые четыре значения дают 1234 (отбраковка), следующие — 1204
+        let mut rng = Fixed(vec![1, 2, 3, 4, 1, 2, 0, 4], 0);
+        let opts = GeneratorOptions {
+            mode: GeneratorMode::Pin,
+            length: 4,
+            ..Default::default()
+        };
+        let g = generate(&opts, &mut rng).unwrap();
+        assert_eq!(g.password, "1204");
+        assert_eq!(rng.1, 8);
+
+        // 10^4 кодов минус содержащие тривиальную тройку
+        let brute = (0..10_000u32)
+            .filter(|n| {
+                let d = [n / 1000, n / 100 % 10, n / 10 % 10, n % 10].map(|x| x as u8);
+                !d.windows(3).any(|w| trivial_triple(w[0], w[1], w[2]))
+            })
+            .count();
+        assert!((g.entropy_bits - (brute as f64).log2()).abs() < 1e-9);
+        assert!(generate(&GeneratorOptions { length: 3, ..opts }, &mut rng).is_err());
+    }
+
+    #[test]
+    fn pronounceable_mode_alternates_syllables() {
+        let mut rng = Fixed(vec![0, 1, 2, 3, 4, 5, 7], 0);
+        let opts = GeneratorOptions {
+            mode: GeneratorMode::Pronounceable,
+            length: 7,
+            ..Default::default()
+        };
+        let g = generate(&opts, &mut rng).unwrap();
+        // b e f o h a + цифра 7, первая буква заглавная
+        assert_eq!(g.password, "Befoha7");
+        let expected = 3.0 * 16f64.log2() + 3.0 * 5f64.log2() + 10f64.log2();
+        assert!((g.entropy_bits - expected).abs() < 1e-9);
+
+        let plain = GeneratorOptions {
+            upper: false,
+            digits: false,
+            length: 5,
+            ..opts
+        };
+        let g = generate(&plain, &mut StdRng::seed_from_u64(5)).unwrap();
+        let chars: Vec<char> = g.password.chars().collect();
+        assert_eq!(chars.len(), 5);
+        for (i, c) in chars.iter().enumerate() {
+            let set = if i % 2 == 0 { CONSONANTS } else { VOWELS };
+            assert!(set.contains(*c), "{}", g.password);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+//! Движок хранилища паролей: SQLite-база с шифрованием записей, бэкапы,
+//! генераторы паролей и проверки. Не зависит от Tauri — его используют
+//! десктопное приложение и скрипты.
+
+pub mod audit;
+pub mod autobackup;
+pub mod backup;
+pub mod breach;
+pub mod clipboard;
+pub mod crypto;
+pub mod db;
+pub mod export;
+pub mod generator;
+pub mod migrations;
+pub mod passphrase;
+pub mod profiles;
+pub mod query;
+pub mod rotation;
+pub mod search;
+pub mod strength;
+
+pub use db::{
+    DataBase, Entry, EntryField, EntryListItem, EntryView, ImportSummary, ResultT, VaultError,
+};
@@ -0,0 +1,308 @@
+//! Версионирование схемы базы. Номер схемы хранится в `PRAGMA user_version`,
+//! миграции применяются по порядку, каждая в своей транзакции вместе с новым
+//! номером — упавшая миграция не оставляет базу наполовину обновлённой.
+//!
+//! Базы, созданные до появления версий, имеют `user_version = 0` и любую из
+//! промежуточных схем, поэтому шаги первых пяти миграций идемпотентны:
+//! `CREATE … IF NOT EXISTS` и добавление столбца только при его отсутствии.
+//! Новые миграции дописываются в конец `MIGRATIONS` и уже могут быть обычным SQL.
+
+use sqlx::{sqlite::SqliteConnection, Executor, Row, SqlitePool};
+
+use super::db::{ResultT, VaultError};
+
+/// Версия схемы, которую понимает этот код.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Последняя схема без номера версии: базы с `user_version = 0` могут быть
+/// в любой схеме до неё включительно.
+#[cfg(test)]
+pub(crate) const UNVERSIONED_LAYOUTS: u32 = 5;
+
+enum Step {
+    Sql(&'static str),
+    /// `ALTER TABLE … ADD COLUMN`, если столбца ещё нет.
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        decl: &'static str,
+    },
+}
+
+struct Migration {
+    description: &'static str,
+    steps: &'static [Step],
+}
+
+/// Миграция `i` переводит базу с версии `i` на `i + 1`.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "vault config and entries",
+        steps: &[Step::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS vault_config (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                kdf_salt BLOB NOT NULL,
+                kdf_params TEXT NOT NULL,
+                key_check BLOB NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS entries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                site TEXT NOT NULL,
+                username TEXT NOT NULL,
+                password_enc BLOB NOT NULL,
+                notes_enc BLOB,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_entries_site ON entries(site);
+            CREATE INDEX IF NOT EXISTS idx_entries_username ON entries(username);
+            "#,
+        )],
+    },
+    Migration {
+        description: "automatic backup schedule",
+        steps: &[Step::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS backup_schedule (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                config TEXT NOT NULL,
+                kdf_salt BLOB NOT NULL,
+                kdf_params TEXT NOT NULL,
+                key_enc BLOB NOT NULL,
+                last_run_at INTEGER
+            );
+            "#,
+        )],
+    },
+    Migration {
+        description: "password strength per entry",
+        steps: &[Step::AddColumn {
+            table: "entries",
+            column: "strength",
+            decl: "INTEGER",
+        }],
+    },
+    Migration {
+        description: "generator profiles",
+        steps: &[
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS generator_profiles (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL UNIQUE,
+                    profile TEXT NOT NULL
+                );
+                "#,
+            ),
+            Step::AddColumn {
+                table: "entries",
+                column: "profile_id",
+                decl: "INTEGER",
+            },
+        ],
+    },
+    Migration {
+        description: "password rotation",
+        steps: &[
+            Step::AddColumn {
+                table: "entries",
+                column: "rotate_days",
+                decl: "INTEGER",
+            },
+            Step::AddColumn {
+                table: "entries",
+                column: "password_changed_at",
+                decl: "INTEGER",
+            },
+            Step::Sql(
+                "UPDATE entries SET password_changed_at = updated_at WHERE password_changed_at IS NULL",
+            ),
+        ],
+    },
+    Migration {
+        description: "entry revisions",
+        steps: &[Step::Sql(
+            "ALTER TABLE entries ADD COLUMN revision INTEGER NOT NULL DEFAULT 1",
+        )],
+    },
+    Migration {
+        description: "entry usage",
+        steps: &[Step::Sql(
+            r#"
+            ALTER TABLE entries ADD COLUMN last_used_at INTEGER;
+            ALTER TABLE entries ADD COLUMN use_count INTEGER NOT NULL DEFAULT 0;
+            "#,
+        )],
+    },
+    Migration {
+        description: "favourites and pins",
+        steps: &[Step::Sql(
+            r#"
+            ALTER TABLE entries ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE entries ADD COLUMN pin_order INTEGER;
+            "#,
+        )],
+    },
+    Migration {
+        description: "automatic backup errors",
+        steps: &[
+            Step::AddColumn {
+                table: "backup_schedule",
+                column: "last_error",
+                decl: "TEXT",
+            },
+            Step::AddColumn {
+                table: "backup_schedule",
+                column: "last_error_at",
+                decl: "INTEGER",
+            },
+        ],
+    },
+];
+
+/// Доводит базу до `SCHEMA_VERSION`. База новее кода не открывается:
+/// старое приложение могло бы испортить данные, о которых не знает.
+pub(crate) async fn migrate(pool: &SqlitePool) -> ResultT<()> {
+    migrate_to(pool, SCHEMA_VERSION).await
+}
+
+pub(crate) async fn migrate_to(pool: &SqlitePool, target: u32) -> ResultT<()> {
+    let mut conn = pool.acquire().await?;
+    let found = schema_version(&mut conn).await?;
+    if found > SCHEMA_VERSION {
+        return Err(VaultError::UnsupportedSchema {
+            found,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    for version in found..target {
+        apply(&mut conn, version).await?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn schema_version(conn: &mut SqliteConnection) -> ResultT<u32> {
+    let row = sqlx::query("PRAGMA user_version").fetch_one(conn).await?;
+    Ok(row.get::<i64, _>(0) as u32)
+}
+
+/// Применяет миграцию `version → version + 1`. `BEGIN IMMEDIATE` сразу берёт
+/// блокировку записи, а версия перечитывается под ней — если базу одновременно
+/// открывают приложение и CLI, миграцию выполнит только один из них.
+async fn apply(conn: &mut SqliteConnection, version: u32) -> ResultT<()> {
+    conn.execute("BEGIN IMMEDIATE").await?;
+    match apply_locked(conn, version).await {
+        Ok(()) => {
+            conn.execute("COMMIT").await?;
+            Ok(())
+        }
+        Err(e) => {
+            conn.execute("ROLLBACK").await?;
+            Err(VaultError::Other(format!(
+                "schema migration {} ({}) failed: {e}",
+                version + 1,
+                MIGRATIONS[version as usize].description
+            )))
+        }
+    }
+}
+
+async fn apply_locked(conn: &mut SqliteConnection, version: u32) -> Result<(), sqlx::Error> {
+    let current: i64 = sqlx::query("PRAGMA user_version")
+        .fetch_one(&mut *conn)
+        .await?
+        .get(0);
+    if current != version as i64 {
+        return Ok(());
+    }
+    let migration = &MIGRATIONS[version as usize];
+    for step in migration.steps {
+        match step {
+            Step::Sql(sql) => {
+                conn.execute(*sql).await?;
+            }
+            Step::AddColumn {
+                table,
+                column,
+                decl,
+            } => {
+                let cols = sqlx::query(&format!("PRAGMA table_info({table})"))
+                    .fetch_all(&mut *conn)
+                    .await?;
+                if !cols.iter().any(|r| r.get::<String, _>("name") == *column) {
+                    conn.execute(
+                        format!("ALTER TABLE {table} ADD COLUMN {column} {decl}").as_str(),
+                    )
+                    .await?;
+                }
+            }
+        }
+    }
+    // PRAGMA не принимает параметры; номер — наше же число
+    conn.execute(format!("PRAGMA user_version = {}", version + 1).as_str())
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use std::str::FromStr;
+    use tempfile::tempdir;
+
+    async fn pool(path: &std::path::Path) -> SqlitePool {
+        let opts = SqliteConnectOptions::from_str(&format!("sqlite://{}", path.display()))
+            .unwrap()
+            .create_if_missing(true);
+        SqlitePoolOptions::new().connect_with(opts).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn migrations_are_ordered_and_refuse_newer_schemas() {
+        let dir = tempdir().unwrap();
+        let pool = pool(&dir.path().join("t.db")).await;
+
+        migrate_to(&pool, 2).await.unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        assert_eq!(schema_version(&mut conn).await.unwrap(), 2);
+        drop(conn);
+
+        migrate(&pool).await.unwrap();
+        // повторный запуск ничего не делает
+        migrate(&pool).await.unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        assert_eq!(schema_version(&mut conn).await.unwrap(), SCHEMA_VERSION);
+
+        conn.execute(format!("PRAGMA user_version = {}", SCHEMA_VERSION + 1).as_str())
+            .await
+            .unwrap();
+        drop(conn);
+        match migrate(&pool).await {
+            Err(VaultError::UnsupportedSchema { found, supported }) => {
+                assert_eq!((found, supported), (SCHEMA_VERSION + 1, SCHEMA_VERSION));
+            }
+            other => panic!("expected UnsupportedSchema, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_migration_rolls_back() {
+        let dir = tempdir().unwrap();
+        let pool = pool(&dir.path().join("t.db")).await;
+        migrate_to(&pool, 1).await.unwrap();
+        // мешающая таблица с тем же именем, но другой схемой: миграция 3 упадёт на ALTER
+        pool.execute("DROP TABLE entries; CREATE VIEW entries AS SELECT 1 AS id")
+            .await
+            .unwrap();
+
+        assert!(migrate(&pool).await.is_err());
+        let mut conn = pool.acquire().await.unwrap();
+        // миграция 2 прошла, 3 откатилась целиком
+        assert_eq!(schema_version(&mut conn).await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashSet;
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::db::{ResultT, VaultError};
+use super::generator::{uniform, GeneratedPassword};
+
+/// Встроенный список: большой список EFF (7776 слов, CC-BY 3.0, © Electronic
+/// Frontier Foundation), 12,9 бита на слово; 6 слов по умолчанию — 77,5 бита.
+const BUILTIN_WORDS: &str = include_str!("wordlists/eff_large_wordlist.txt");
+
+pub(crate) const MAX_WORDS: usize = 32;
+/// Большой список EFF занимает около 100 КБ; больше мегабайта — явно не список слов.
+const MAX_WORDLIST_BYTES: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Capitalize {
+    #[default]
+    None,
+    /// Каждое слово с заглавной буквы.
+    First,
+    /// Каждое слово целиком заглавными.
+    Upper,
+    /// Случайно: слово с заглавной или нет (+1 бит на слово).
+    Random,
+}
+
+/// Параметры генератора парольных фраз (diceware).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PassphraseOptions {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: Capitalize,
+    /// Дописать случайную цифру к случайному слову.
+    pub digit: bool,
+    /// Свой список слов текстом (не путь к файлу): по слову в строке,
+    /// формат EFF (`11111<TAB>abacus`) тоже подходит. Не больше 1 МБ.
+    pub wordlist: Option<String>,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        Self {
+            words: 6,
+            separator: "-".into(),
+            capitalize: Capitalize::None,
+            digit: false,
+            wordlist: None,
+        }
+    }
+}
+
+/// Слова из текста списка: последний столбец каждой непустой строки.
+pub fn parse_wordlist(text: &str) -> ResultT<Vec<String>> {
+    if text.len() > MAX_WORDLIST_BYTES {
+        return Err(VaultError::Other("wordlist is too large".into()));
+    }
+    let words: Vec<String> = text
+        .lines()
+        .filter_map(|l| l.split_whitespace().last())
+        .map(str::to_owned)
+        .collect();
+    let unique: HashSet<&str> = words.iter().map(String::as_str).collect();
+    if unique.len() != words.len() {
+        return Err(VaultError::Other(
+            "wordlist contains duplicate words".into(),
+        ));
+    }
+    if words.len() < 2 {
+        return Err(VaultError::Other("wordlist is too short".into()));
+    }
+    Ok(words)
+}
+
+pub fn builtin_wordlist() -> Vec<String> {
+    BUILTIN_WORDS
+        .lines()
+        .filter_map(|l| l.split_whitespace().last())
+        .map(str::to_owned)
+        .collect()
+}
+
+pub fn generate<R: RngCore + ?Sized>(
+    opts: &PassphraseOptions,
+    list: &[String],
+    rng: &mut R,
+) -> ResultT<GeneratedPassword> {
+    if opts.words == 0 || opts.words > MAX_WORDS {
+        return Err(VaultError::Other(format!(
+            "word count must be between 1 and {MAX_WORDS}"
+        )));
+    }
+    let mut entropy_bits = opts.words as f64 * (list.len() as f64).log2();
+
+    let mut words: Vec<String> = (0..opts.words)
+        .map(|_| list[uniform(rng, list.len())].clone())
+        .collect();
+    for w in words.iter_mut() {
+        let upper = match opts.capitalize {
+            Capitalize::None => continue,
+            Capitalize::Upper => {
+                *w = w.to_uppercase();
+                continue;
+            }
+            Capitalize::First => true,
+            Capitalize::Random => uniform(rng, 2) == 1,
+        };
+        if upper {
+            let mut chars = w.chars();
+            if let Some(first) = chars.next() {
+                *w = first.to_uppercase().chain(chars).collect();
+            }
+        }
+    }
+    if opts.capitalize == Capitalize::Random {
+        entropy_bits += opts.words as f64;
+    }
+    if opts.digit {
+        let i = uniform(rng, words.len());
+        let d = uniform(rng, 10);
+        words[i].push(char::from(b'0' + d as u8));
+        entropy_bits += (10.0 * words.len() as f64).log2();
+    }
+
+    let password = words.join(&opts.separator);
+    zeroize::Zeroize::zeroize(&mut words);
+    Ok(GeneratedPassword {
+        password,
+        entropy_bits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn builtin_list_is_valid() {
+        let list = parse_wordlist(BUILTIN_WORDS).unwrap();
+        assert_eq!(list.len(), 7776);
+        assert_eq!(list, builtin_wordlist());
+
+        // по умолчанию 6 бросков пяти костей
+        let mut rng = StdRng::seed_from_u64(1);
+        let g = generate(&PassphraseOptions::default(), &list, &mut rng).unwrap();
+        assert_eq!(g.password.split('-').count(), 6);
+        assert!((g.entropy_bits - 6.0 * 7776f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn eff_format_and_options() {
+        let list =
+            parse_wordlist("11111\tabacus\n11112\tabdomen\n\n11113\tabdominal\n11114\tabide\n")
+                .unwrap();
+        assert_eq!(list, ["abacus", "abdomen", "abdominal", "abide"]);
+        assert!(parse_wordlist("one\none\n").is_err());
+        assert!(parse_wordlist(&"word\n".repeat(MAX_WORDLIST_BYTES)).is_err());
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let opts = PassphraseOptions {
+            words: 5,
+            separator: " ".into(),
+            capitalize: Capitalize::First,
+            digit: true,
+            wordlist: None,
+        };
+        let g = generate(&opts, &list, &mut rng).unwrap();
+        let parts: Vec<&str> = g.password.split(' ').collect();
+        assert_eq!(parts.len(), 5);
+        assert!(parts.iter().all(|p| p.starts_with('A')));
+        assert_eq!(
+            parts
+                .iter()
+                .filter(|p| p.ends_with(|c: char| c.is_ascii_digit()))
+                .count(),
+            1
+        );
+        // 5 слов по 2 бита + цифра в одном из 5 слов
+        assert!((g.entropy_bits - (10.0 + 50f64.log2())).abs() < 1e-9);
+
+        let opts = PassphraseOptions {
+            capitalize: Capitalize::Random,
+            digit: false,
+            ..opts
+        };
+        let g = generate(&opts, &list, &mut rng).unwrap();
+        assert!((g.entropy_bits - 15.0).abs() < 1e-9);
+        assert!(generate(&PassphraseOptions { words: 0, ..opts }, &list, &mut rng).is_err());
+    }
+}
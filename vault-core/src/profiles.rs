@@ -0,0 +1,271 @@
+use rand::RngCore;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::db::{ResultT, VaultError};
+use super::generator::{
+    self, Charset, GeneratedPassword, GeneratorMode, GeneratorOptions, MAX_LENGTH,
+};
+
+/// Сколько кандидатов минимум генерируется при регулярных ограничениях:
+/// по доле подошедших оценивается потеря энтропии.
+const SAMPLE: usize = 256;
+const MAX_ATTEMPTS: usize = 10_000;
+
+/// Именованный профиль генератора под правила конкретной системы.
+/// Хранится в `generator_profiles` как JSON.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GeneratorProfile {
+    pub id: i64,
+    pub name: String,
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Включённые классы обязательны: каждый встретится хотя бы раз.
+    pub lower: bool,
+    pub upper: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// Допустимые спецсимволы; `None` — стандартный набор.
+    pub symbol_set: Option<String>,
+    pub exclude: String,
+    /// Регулярные выражения, которым пароль обязан соответствовать (например `^[A-Za-z]`).
+    pub must_match: Vec<String>,
+    /// Шаблоны сайтов с `*`, для которых профиль применяется автоматически.
+    pub site_patterns: Vec<String>,
+}
+
+impl Default for GeneratorProfile {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: String::new(),
+            min_length: 12,
+            max_length: 64,
+            lower: true,
+            upper: true,
+            digits: true,
+            symbols: true,
+            symbol_set: None,
+            exclude: String::new(),
+            must_match: Vec::new(),
+            site_patterns: Vec::new(),
+        }
+    }
+}
+
+impl GeneratorProfile {
+    pub(crate) fn validate(&self) -> ResultT<()> {
+        if self.name.trim().is_empty() {
+            return Err(VaultError::Other("profile name is required".into()));
+        }
+        if self.min_length == 0 || self.min_length > self.max_length || self.max_length > MAX_LENGTH
+        {
+            return Err(VaultError::Other(format!(
+                "profile length range must be within 1..={MAX_LENGTH}"
+            )));
+        }
+        if self.site_patterns.iter().any(|p| p.trim().is_empty()) {
+            return Err(VaultError::Other("empty site pattern".into()));
+        }
+        self.compile()?;
+        Charset::new(&self.options(self.max_length)).map(|_| ())
+    }
+
+    fn compile(&self) -> ResultT<Vec<Regex>> {
+        self.must_match
+            .iter()
+            .map(|p| {
+                Regex::new(p).map_err(|e| VaultError::Other(format!("bad pattern {p:?}: {e}")))
+            })
+            .collect()
+    }
+
+    /// Параметры генератора: режим из запроса, длина из запроса, зажатая в диапазон
+    /// профиля. Минимумы классов из запроса сохраняются, если класс профилем разрешён.
+    pub(crate) fn apply(&self, requested: &GeneratorOptions) -> GeneratorOptions {
+        let mut opts = self.options(requested.length.clamp(self.min_length, self.max_length));
+        opts.mode = requested.mode;
+        opts.min_lower = if self.lower { requested.min_lower } else { 0 };
+        opts.min_upper = if self.upper { requested.min_upper } else { 0 };
+        opts.min_digits = if self.digits { requested.min_digits } else { 0 };
+        opts.min_symbols = if self.symbols {
+            requested.min_symbols
+        } else {
+            0
+        };
+        opts.exclude.push_str(&requested.exclude);
+        opts.avoid_ambiguous = requested.avoid_ambiguous;
+        opts
+    }
+
+    fn options(&self, length: usize) -> GeneratorOptions {
+        GeneratorOptions {
+            length,
+            lower: self.lower,
+            upper: self.upper,
+            digits: self.digits,
+            symbols: self.symbols,
+            symbol_set: self.symbol_set.clone(),
+            exclude: self.exclude.clone(),
+            ..GeneratorOptions::default()
+        }
+    }
+
+    /// Насколько шаблон сайта подходит к `site`: `None` — не подходит,
+    /// иначе число совпавших буквальных символов (чем больше, тем точнее).
+    pub(crate) fn site_match(&self, site: &str) -> Option<usize> {
+        let site = site.trim().to_lowercase();
+        self.site_patterns
+            .iter()
+            .filter(|p| glob_match(&p.trim().to_lowercase(), &site))
+            .map(|p| p.chars().filter(|&c| c != '*').count())
+            .max()
+    }
+}
+
+/// Сопоставление с шаблоном, где `*` — любая (в том числе пустая) подстрока.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text.ends_with(last) || text.len() < first.len() + last.len() {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Пароль по профилю. Регулярные ограничения выполняются отбраковкой: первый подошедший
+/// кандидат распределён равномерно среди подходящих, а доля подошедших из не менее чем
+/// `SAMPLE` попыток даёт оценку того, сколько энтропии отнимают ограничения.
+/// В режимах PIN и произносимых паролей кандидатов даёт генератор режима, и они
+/// отбраковываются ещё и по алфавиту и обязательным классам профиля.
+pub(crate) fn generate<R: RngCore + ?Sized>(
+    profile: &GeneratorProfile,
+    requested: &GeneratorOptions,
+    rng: &mut R,
+) -> ResultT<GeneratedPassword> {
+    let opts = profile.apply(requested);
+    let charset = Charset::new(&opts)?;
+    let patterns = profile.compile()?;
+    let random = opts.mode == GeneratorMode::Random;
+    if random && patterns.is_empty() {
+        return Ok(GeneratedPassword {
+            password: charset.sample(rng),
+            entropy_bits: charset.entropy_bits,
+        });
+    }
+
+    let mut found = None;
+    let mut entropy_bits = charset.entropy_bits;
+    let (mut accepted, mut total) = (0usize, 0usize);
+    while total < MAX_ATTEMPTS && (total < SAMPLE || found.is_none()) {
+        let mut candidate = if random {
+            charset.sample(rng)
+        } else {
+            let g = generator::generate(&opts, rng)?;
+            entropy_bits = g.entropy_bits;
+            g.password
+        };
+        total += 1;
+        if (random || charset.admits(&candidate)) && patterns.iter().all(|r| r.is_match(&candidate))
+        {
+            accepted += 1;
+            if found.is_none() {
+                found = Some(candidate);
+                continue;
+            }
+        }
+        zeroize::Zeroize::zeroize(&mut candidate);
+    }
+    let password = found.ok_or_else(|| {
+        VaultError::Other(if random {
+            format!(
+                "profile {:?}: no password satisfies its patterns",
+                profile.name
+            )
+        } else {
+            format!(
+                "profile {:?}: no {:?} password satisfies its rules",
+                profile.name, opts.mode
+            )
+        })
+    })?;
+    Ok(GeneratedPassword {
+        password,
+        entropy_bits: entropy_bits + (accepted as f64 / total as f64).log2(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("*.corp.example", "git.corp.example"));
+        assert!(!glob_match("*.corp.example", "corp.example"));
+        assert!(glob_match("intranet", "intranet"));
+        assert!(glob_match("vpn*.corp*", "vpn2.corp.example"));
+        assert!(!glob_match("a*a", "a"));
+
+        let p = GeneratorProfile {
+            site_patterns: vec!["*.Corp.example".into(), "*".into()],
+            ..Default::default()
+        };
+        assert_eq!(p.site_match("HR.corp.example"), Some(13));
+        assert_eq!(p.site_match("other.org"), Some(0));
+    }
+
+    #[test]
+    fn profile_rules_are_honoured() {
+        let profile = GeneratorProfile {
+            name: "legacy".into(),
+            min_length: 8,
+            max_length: 16,
+            symbol_set: Some("!#%".into()),
+            must_match: vec!["^[A-Za-z]".into()],
+            ..Default::default()
+        };
+        profile.validate().unwrap();
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let requested = GeneratorOptions::default();
+        for _ in 0..50 {
+            let g = generate(&profile, &requested, &mut rng).unwrap();
+            let p = &g.password;
+            assert_eq!(p.len(), 16);
+            assert!(p.starts_with(|c: char| c.is_ascii_alphabetic()));
+            assert!(p.chars().any(|c| "!#%".contains(c)));
+            assert!(p
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#%".contains(c)));
+            // первая буква — 52 из 65 символов
+            let full = Charset::new(&profile.apply(&requested))
+                .unwrap()
+                .entropy_bits;
+            assert!(g.entropy_bits < full && g.entropy_bits > full - 1.0);
+        }
+
+        let bad = GeneratorProfile {
+            must_match: vec!["(".into()],
+            ..profile.clone()
+        };
+        assert!(bad.validate().is_err());
+        let unreachable = GeneratorProfile {
+            must_match: vec!["^$".into()],
+            ..profile
+        };
+        assert!(generate(&unreachable, &requested, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Запрос списка записей: фильтры, сортировка и страницы. SQL собирается
+//! через `QueryBuilder`, все значения идут параметрами.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite};
+
+use super::db::EntryListItem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Site,
+    Username,
+    Created,
+    Updated,
+    /// По оценке стойкости; записи без оценки всегда в конце.
+    Strength,
+    /// По последнему использованию; ни разу не использованные — в конце.
+    #[serde(rename = "last_used")]
+    LastUsed,
+    /// По числу использований.
+    #[serde(rename = "use_count")]
+    UseCount,
+}
+
+/// Параметры `DataBase::query_entries`. Даты — секунды Unix; `*_from`
+/// включительно, `*_to` — нет. Фильтра по типу записи нет намеренно: у записей
+/// пока один тип (логин с паролем), появится тип — появится и фильтр.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListQuery {
+    /// Подстрока сайта или логина.
+    pub search: Option<String>,
+    pub sort: SortKey,
+    pub descending: bool,
+    pub offset: usize,
+    /// `None` — до конца списка.
+    pub limit: Option<usize>,
+    pub created_from: Option<i64>,
+    pub created_to: Option<i64>,
+    pub updated_from: Option<i64>,
+    pub updated_to: Option<i64>,
+    /// `Some(true)` — только с заметками, `Some(false)` — только без.
+    pub has_notes: Option<bool>,
+    /// Закреплённые записи (в их ручном порядке), затем избранные, затем остальные;
+    /// внутри групп — по `sort`.
+    pub favorites_first: bool,
+}
+
+impl Default for ListQuery {
+    fn default() -> Self {
+        Self {
+            search: None,
+            sort: SortKey::Updated,
+            descending: true,
+            offset: 0,
+            limit: None,
+            created_from: None,
+            created_to: None,
+            updated_from: None,
+            updated_to: None,
+            has_notes: None,
+            favorites_first: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EntryPage {
+    /// Сколько записей подходит под фильтры, без учёта `offset`/`limit`.
+    pub total: usize,
+    pub items: Vec<EntryListItem>,
+}
+
+impl ListQuery {
+    /// Дописывает `WHERE …` по фильтрам запроса.
+    pub(crate) fn push_filters(&self, qb: &mut QueryBuilder<'_, Sqlite>) {
+        qb.push(" WHERE 1=1");
+        if let Some(s) = self
+            .search
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            let like = format!("%{s}%");
+            qb.push(" AND (site LIKE ")
+                .push_bind(like.clone())
+                .push(" OR username LIKE ")
+                .push_bind(like)
+                .push(")");
+        }
+        let ranges = [
+            ("created_at >= ", self.created_from),
+            ("created_at < ", self.created_to),
+            ("updated_at >= ", self.updated_from),
+            ("updated_at < ", self.updated_to),
+        ];
+        for (cond, value) in ranges {
+            if let Some(v) = value {
+                qb.push(" AND ").push(cond).push_bind(v);
+            }
+        }
+        match self.has_notes {
+            Some(true) => qb.push(" AND notes_enc IS NOT NULL"),
+            Some(false) => qb.push(" AND notes_enc IS NULL"),
+            None => qb,
+        };
+    }
+
+    /// `ORDER BY … LIMIT … OFFSET …`; порядок всегда полный (последний ключ — `id`),
+    /// чтобы страницы не пересекались.
+    pub(crate) fn push_page(&self, qb: &mut QueryBuilder<'_, Sqlite>) {
+        let dir = if self.descending { "DESC" } else { "ASC" };
+        let order = match self.sort {
+            SortKey::Site => {
+                format!("site COLLATE NOCASE {dir}, username COLLATE NOCASE {dir}, id {dir}")
+            }
+            SortKey::Username => {
+                format!("username COLLATE NOCASE {dir}, site COLLATE NOCASE {dir}, id {dir}")
+            }
+            SortKey::Created => format!("created_at {dir}, id {dir}"),
+            SortKey::Updated => format!("updated_at {dir}, id {dir}"),
+            SortKey::Strength => {
+                format!("strength IS NULL, strength {dir}, updated_at DESC, id DESC")
+            }
+            SortKey::LastUsed => format!("last_used_at IS NULL, last_used_at {dir}, id {dir}"),
+            SortKey::UseCount => {
+                format!("use_count {dir}, last_used_at IS NULL, last_used_at DESC, id DESC")
+            }
+        };
+        qb.push(" ORDER BY ");
+        if self.favorites_first {
+            qb.push("pin_order IS NULL, pin_order, favorite DESC, ");
+        }
+        qb.push(order);
+        // в SQLite отрицательный LIMIT — без ограничения
+        let limit = self.limit.map_or(-1, |l| l.min(i64::MAX as usize) as i64);
+        qb.push(" LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(self.offset as i64);
+    }
+}
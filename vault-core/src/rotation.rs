@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use super::audit::AuditEntryRef;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// Запись, пароль которой пора сменить по заданному интервалу ротации.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RotationReminder {
+    pub entry: AuditEntryRef,
+    pub rotate_days: u32,
+    /// Когда пароль меняли в последний раз.
+    pub changed_at: i64,
+    pub expires_at: i64,
+    /// Сколько полных дней осталось; отрицательное — сколько дней как просрочен.
+    pub days_left: i64,
+    pub expired: bool,
+}
+
+/// Напоминание для записи, если срок истёк или истекает в ближайшие `within_days` дней.
+pub(crate) fn reminder(
+    entry: AuditEntryRef,
+    rotate_days: u32,
+    changed_at: i64,
+    now: i64,
+    within_days: u32,
+) -> Option<RotationReminder> {
+    let expires_at = changed_at + rotate_days as i64 * DAY;
+    if expires_at - now > within_days as i64 * DAY {
+        return None;
+    }
+    Some(RotationReminder {
+        entry,
+        rotate_days,
+        changed_at,
+        expires_at,
+        days_left: (expires_at - now) / DAY,
+        expired: expires_at <= now,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn due_soon_and_expired() {
+        let e = AuditEntryRef {
+            id: 1,
+            site: "vpn".into(),
+            username: "alice".into(),
+        };
+        let now = 1_000 * DAY;
+
+        assert_eq!(reminder(e.clone(), 90, now - 10 * DAY, now, 14), None);
+
+        let soon = reminder(e.clone(), 90, now - 80 * DAY, now, 14).unwrap();
+        assert_eq!((soon.days_left, soon.expired), (10, false));
+
+        let late = reminder(e, 90, now - 91 * DAY - 1, now, 0).unwrap();
+        assert!(late.expired);
+        assert_eq!(late.days_left, -1);
+    }
+}
@@ -0,0 +1,279 @@
+//! Поиск по записям с ранжированием: точный хост сайта, целое слово, начало
+//! слова, подстрока и, наконец, опечатки (расстояние Дамерау–Левенштейна).
+//! Всё считается в памяти; заметки расшифровываются только на время поиска.
+
+use std::cmp::Reverse;
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::db::EntryListItem;
+
+/// Параметры поиска.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub query: String,
+    /// Искать и в заметках; требует разблокированного хранилища.
+    pub in_notes: bool,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            in_notes: false,
+            offset: 0,
+            limit: 50,
+        }
+    }
+}
+
+/// Насколько хорошо совпало — от слабого к сильному; по этому в первую очередь сортируется выдача.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    Fuzzy,
+    Substring,
+    Prefix,
+    Exact,
+    /// Запрос — это хост сайта записи (`github.com` для `https://www.github.com/login`).
+    Host,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchField {
+    Notes,
+    Username,
+    Site,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub entry: EntryListItem,
+    /// Поле, давшее лучшее совпадение.
+    pub field: MatchField,
+    /// Самое слабое совпадение среди слов запроса.
+    pub kind: MatchKind,
+    pub score: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchResults {
+    /// Всего совпадений, без учёта `offset`/`limit`.
+    pub total: usize,
+    pub hits: Vec<SearchHit>,
+}
+
+/// Сайт без схемы, `www.`, пути и регистра.
+pub fn normalize_host(site: &str) -> String {
+    let s = site.trim().to_lowercase();
+    let s = s.split_once("://").map_or(s.as_str(), |(_, rest)| rest);
+    let s = s.strip_prefix("www.").unwrap_or(s);
+    let end = s.find(['/', '?', '#']).unwrap_or(s.len());
+    s[..end].to_owned()
+}
+
+/// Слова в нижнем регистре: всё, что между небуквенно-цифровыми символами.
+pub fn tokens(s: &str) -> Vec<String> {
+    Zeroizing::new(s.to_lowercase())
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Сопоставляет запрос с записью; `None`, если хотя бы одно слово запроса нигде не нашлось.
+pub fn score(
+    query: &str,
+    site: &str,
+    username: &str,
+    notes: Option<&str>,
+) -> Option<(MatchField, MatchKind, u32)> {
+    let host = normalize_host(query);
+    if !host.is_empty() && host == normalize_host(site) {
+        return Some((
+            MatchField::Site,
+            MatchKind::Host,
+            points(MatchKind::Host, MatchField::Site),
+        ));
+    }
+
+    // копии полей (и расшифрованных заметок) затираются после сравнения
+    type Field = (MatchField, Zeroizing<String>, Zeroizing<Vec<String>>);
+    let fields: Vec<Field> = [
+        (MatchField::Site, Some(site)),
+        (MatchField::Username, Some(username)),
+        (MatchField::Notes, notes),
+    ]
+    .into_iter()
+    .filter_map(|(f, text)| {
+        text.map(|t| {
+            (
+                f,
+                Zeroizing::new(t.to_lowercase()),
+                Zeroizing::new(tokens(t)),
+            )
+        })
+    })
+    .collect();
+
+    let query_tokens = tokens(query);
+    if query_tokens.is_empty() {
+        return None;
+    }
+    let mut weakest = MatchKind::Host;
+    let mut best: Option<(u32, MatchField)> = None;
+    let mut total = 0;
+    for q in &query_tokens {
+        let (kind, field) = fields
+            .iter()
+            .filter_map(|(f, text, toks)| match_token(q, text, toks).map(|k| (k, *f)))
+            .max()?;
+        let p = points(kind, field);
+        total += p;
+        weakest = weakest.min(kind);
+        if best.is_none_or(|(bp, _)| p > bp) {
+            best = Some((p, field));
+        }
+    }
+    best.map(|(_, field)| (field, weakest, total))
+}
+
+fn points(kind: MatchKind, field: MatchField) -> u32 {
+    let kind = match kind {
+        MatchKind::Host => 100,
+        MatchKind::Exact => 40,
+        MatchKind::Prefix => 30,
+        MatchKind::Substring => 20,
+        MatchKind::Fuzzy => 10,
+    };
+    let field = match field {
+        MatchField::Site => 3,
+        MatchField::Username => 2,
+        MatchField::Notes => 1,
+    };
+    kind + field
+}
+
+fn match_token(q: &str, text: &str, toks: &[String]) -> Option<MatchKind> {
+    if toks.iter().any(|t| t == q) {
+        return Some(MatchKind::Exact);
+    }
+    if toks.iter().any(|t| t.starts_with(q)) {
+        return Some(MatchKind::Prefix);
+    }
+    if text.contains(q) {
+        return Some(MatchKind::Substring);
+    }
+    let q: Vec<char> = q.chars().collect();
+    let max = match q.len() {
+        0..=2 => return None,
+        3..=5 => 1,
+        _ => 2,
+    };
+    toks.iter()
+        .any(|t| {
+            let t: Zeroizing<Vec<char>> = Zeroizing::new(t.chars().collect());
+            // недописанное слово с опечаткой: сравниваем и с началом слова
+            let head = &t[..t.len().min(q.len() + max)];
+            osa_distance(&q, &t) <= max || osa_distance(&q, head) <= max
+        })
+        .then_some(MatchKind::Fuzzy)
+}
+
+/// Расстояние Дамерау–Левенштейна (вариант с оптимальным выравниванием строк).
+fn osa_distance(a: &[char], b: &[char]) -> usize {
+    let mut prev2: Vec<usize> = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        cur[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                cur[j] = cur[j].min(prev2[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut prev2, &mut prev);
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+/// Сортирует совпадения (сила совпадения, очки, свежесть) и вырезает страницу.
+pub(crate) fn page(mut hits: Vec<SearchHit>, opts: &SearchOptions) -> SearchResults {
+    hits.sort_by_key(|h| {
+        (
+            Reverse(h.kind),
+            Reverse(h.score),
+            Reverse(h.entry.updated_at),
+            Reverse(h.entry.id),
+        )
+    });
+    let total = hits.len();
+    let hits = hits
+        .into_iter()
+        .skip(opts.offset)
+        .take(opts.limit)
+        .collect();
+    SearchResults { total, hits }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(query: &str, site: &str) -> Option<MatchKind> {
+        score(query, site, "alice", None).map(|(_, k, _)| k)
+    }
+
+    #[test]
+    fn matches_are_ranked_by_kind() {
+        assert_eq!(
+            kind("github.com", "https://www.GitHub.com/login"),
+            Some(MatchKind::Host)
+        );
+        assert_eq!(kind("github", "github.com"), Some(MatchKind::Exact));
+        assert_eq!(kind("git", "github.com"), Some(MatchKind::Prefix));
+        assert_eq!(kind("hub", "github.com"), Some(MatchKind::Substring));
+        assert_eq!(kind("gihtub", "github.com"), Some(MatchKind::Fuzzy));
+        assert_eq!(kind("githbu", "github.com"), Some(MatchKind::Fuzzy));
+        // недописанное слово с опечаткой
+        assert_eq!(kind("gitlba", "gitlab.example.org"), Some(MatchKind::Fuzzy));
+        assert_eq!(kind("gmail", "github.com"), None);
+        // короткие слова опечаток не прощают
+        assert_eq!(kind("gx", "github.com"), None);
+    }
+
+    #[test]
+    fn every_query_word_must_match_somewhere() {
+        let (field, kind, _) = score("git alice", "github.com", "alice", None).unwrap();
+        // поле — с самым сильным совпадением, вид — самое слабое из слов
+        assert_eq!((field, kind), (MatchField::Username, MatchKind::Prefix));
+        assert!(score("git bob", "github.com", "alice", None).is_none());
+
+        assert!(score("vpn", "corp.example", "alice", None).is_none());
+        let (field, _, _) = score("vpn", "corp.example", "alice", Some("VPN token")).unwrap();
+        assert_eq!(field, MatchField::Notes);
+    }
+
+    #[test]
+    fn osa_counts_transpositions_once() {
+        let d = |a: &str, b: &str| {
+            osa_distance(
+                &a.chars().collect::<Vec<_>>(),
+                &b.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(d("github", "gihtub"), 1);
+        assert_eq!(d("github", "gthiub"), 2);
+        assert_eq!(d("github", "githbu"), 1);
+        assert_eq!(d("kitten", "sitting"), 3);
+        assert_eq!(d("", "abc"), 3);
+    }
+}
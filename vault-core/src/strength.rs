@@ -0,0 +1,729 @@
+//! Оценка стойкости пароля в духе zxcvbn: пароль раскладывается на угадываемые
+//! фрагменты (словарные слова, клавиатурные дорожки, последовательности, повторы,
+//! даты), и ищется разбиение с минимальным числом попыток перебора.
+
+use std::{
+    collections::HashMap,
+    sync::OnceLock,
+};
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// Результат оценки стойкости.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StrengthReport {
+    /// 0 (угадывается мгновенно) … 4 (очень стойкий).
+    pub score: u8,
+    /// log2 оценки числа попыток.
+    pub entropy_bits: f64,
+    pub guesses_log10: f64,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+const COMMON_PASSWORDS: &str = include_str!("wordlists/common_passwords.txt");
+const ENGLISH_WORDS: &str = include_str!("wordlists/english_words.txt");
+
+/// Длиннее анализировать нет смысла: хвост считается перебором.
+const MAX_ANALYZED: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dict {
+    Passwords,
+    English,
+    UserInputs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Pattern {
+    Dictionary {
+        dict: Dict,
+        l33t: bool,
+        reversed: bool,
+    },
+    Spatial {
+        turns: usize,
+    },
+    Sequence,
+    Repeat,
+    Date,
+    Bruteforce,
+}
+
+#[derive(Debug, Clone)]
+struct Match {
+    i: usize,
+    /// включительно
+    j: usize,
+    pattern: Pattern,
+    log10: f64,
+}
+
+fn ranked(list: &'static str) -> HashMap<&'static str, usize> {
+    list.lines()
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .enumerate()
+        .map(|(i, w)| (w, i + 1))
+        .collect()
+}
+
+fn dictionaries() -> &'static [(Dict, HashMap<&'static str, usize>)] {
+    static DICTS: OnceLock<Vec<(Dict, HashMap<&'static str, usize>)>> = OnceLock::new();
+    DICTS.get_or_init(|| {
+        vec![
+            (Dict::Passwords, ranked(COMMON_PASSWORDS)),
+            (Dict::English, ranked(ENGLISH_WORDS)),
+        ]
+    })
+}
+
+/// Оценивает пароль. `user_inputs` (сайт, логин и т.п.) считаются самыми очевидными словами.
+pub fn estimate(password: &str, user_inputs: &[&str]) -> StrengthReport {
+    estimate_in_year(password, user_inputs, OffsetDateTime::now_utc().year())
+}
+
+/// То же, но годы в датах сравниваются с `year`, а не с текущим.
+pub fn estimate_in_year(password: &str, user_inputs: &[&str], year: i32) -> StrengthReport {
+    let chars: Vec<char> = password.chars().collect();
+    if chars.is_empty() {
+        return StrengthReport {
+            score: 0,
+            entropy_bits: 0.0,
+            guesses_log10: 0.0,
+            warning: Some("Enter a password".into()),
+            suggestions: Vec::new(),
+        };
+    }
+
+    let user: HashMap<String, usize> = user_inputs
+        .iter()
+        .flat_map(|s| s.split(|c: char| !c.is_alphanumeric()))
+        .map(str::to_lowercase)
+        .filter(|w| w.chars().count() >= 3)
+        .enumerate()
+        .map(|(i, w)| (w, i + 1))
+        .collect();
+
+    let card = cardinality(&chars);
+    let head = &chars[..chars.len().min(MAX_ANALYZED)];
+    let (mut log10, path) = most_guessable(head, &user, card, year);
+    log10 += (chars.len() - head.len()) as f64 * card.log10();
+
+    let score = match log10 {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => 4,
+    };
+    let (warning, suggestions) = feedback(score, &path, &chars);
+    StrengthReport {
+        score,
+        entropy_bits: log10 * std::f64::consts::LOG2_10,
+        guesses_log10: log10,
+        warning,
+        suggestions,
+    }
+}
+
+/// Размер алфавита для перебора по классам символов, встречающимся в пароле.
+fn cardinality(chars: &[char]) -> f64 {
+    let has = |f: fn(&char) -> bool| chars.iter().any(f);
+    let mut n = 0.0;
+    if has(char::is_ascii_lowercase) {
+        n += 26.0;
+    }
+    if has(char::is_ascii_uppercase) {
+        n += 26.0;
+    }
+    if has(char::is_ascii_digit) {
+        n += 10.0;
+    }
+    if has(char::is_ascii_punctuation) || chars.contains(&' ') {
+        n += 33.0;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        n += 100.0;
+    }
+    f64::max(n, 10.0)
+}
+
+/// Разбиение с минимальной оценкой: Σ log10(попыток фрагмента) + log10(k!) за k фрагментов.
+fn most_guessable(
+    chars: &[char],
+    user: &HashMap<String, usize>,
+    card: f64,
+    year: i32,
+) -> (f64, Vec<Match>) {
+    let n = chars.len();
+    let mut matches = omnimatch(chars, user, year);
+    for i in 0..n {
+        for j in i..n {
+            matches.push(Match {
+                i,
+                j,
+                pattern: Pattern::Bruteforce,
+                log10: (j - i + 1) as f64 * card.log10(),
+            });
+        }
+    }
+    // фрагмент, не покрывающий весь пароль, не может быть проще нескольких десятков попыток
+    for m in matches.iter_mut() {
+        if m.pattern != Pattern::Bruteforce && m.j - m.i + 1 < n {
+            let min = if m.i == m.j { 1.0 } else { 50f64.log10() };
+            m.log10 = m.log10.max(min);
+        }
+    }
+
+    let mut by_end: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (idx, m) in matches.iter().enumerate() {
+        by_end[m.j].push(idx);
+    }
+
+    // best[k][l]: префикс длины k, разбитый на l фрагментов
+    let mut best = vec![vec![f64::INFINITY; n + 1]; n + 1];
+    let mut back = vec![vec![usize::MAX; n + 1]; n + 1];
+    best[0][0] = 0.0;
+    for k in 1..=n {
+        for &idx in &by_end[k - 1] {
+            let m = &matches[idx];
+            for l in 0..k {
+                let prev = best[m.i][l];
+                if prev.is_finite() && prev + m.log10 < best[k][l + 1] {
+                    best[k][l + 1] = prev + m.log10;
+                    back[k][l + 1] = idx;
+                }
+            }
+        }
+    }
+
+    let mut log_fact = 0.0;
+    let mut total = f64::INFINITY;
+    let mut segments = 0;
+    for (l, &cost) in best[n].iter().enumerate().skip(1) {
+        log_fact += (l as f64).log10();
+        if cost + log_fact < total {
+            total = cost + log_fact;
+            segments = l;
+        }
+    }
+
+    let mut path = Vec::with_capacity(segments);
+    let (mut k, mut l) = (n, segments);
+    while k > 0 {
+        let m = matches[back[k][l]].clone();
+        k = m.i;
+        l -= 1;
+        path.push(m);
+    }
+    path.reverse();
+    (total, path)
+}
+
+fn omnimatch(chars: &[char], user: &HashMap<String, usize>, year: i32) -> Vec<Match> {
+    let mut out = Vec::new();
+    dictionary_matches(chars, user, &mut out);
+    spatial_matches(chars, &mut out);
+    sequence_matches(chars, &mut out);
+    repeat_matches(chars, user, year, &mut out);
+    date_matches(chars, year, &mut out);
+    out
+}
+
+// ---------- словари ----------
+
+const L33T: [&[(char, char)]; 2] = [
+    &[
+        ('4', 'a'), ('@', 'a'), ('8', 'b'), ('(', 'c'), ('3', 'e'), ('6', 'g'), ('9', 'g'),
+        ('1', 'i'), ('!', 'i'), ('|', 'i'), ('0', 'o'), ('$', 's'), ('5', 's'), ('7', 't'),
+        ('+', 't'), ('2', 'z'),
+    ],
+    &[('1', 'l'), ('|', 'l'), ('7', 'l')],
+];
+
+fn unleet(word: &[char], table: &[(char, char)]) -> Option<(String, usize)> {
+    let mut subs = 0;
+    let s: String = word
+        .iter()
+        .map(|&c| match table.iter().find(|(from, _)| *from == c) {
+            Some(&(_, to)) => {
+                subs += 1;
+                to
+            }
+            None => c,
+        })
+        .collect();
+    (subs > 0).then_some((s, subs))
+}
+
+fn lookup(word: &str, user: &HashMap<String, usize>) -> Option<(Dict, usize)> {
+    if let Some(&r) = user.get(word) {
+        return Some((Dict::UserInputs, r));
+    }
+    dictionaries()
+        .iter()
+        .filter_map(|(d, m)| m.get(word).map(|&r| (*d, r)))
+        .min_by_key(|&(_, r)| r)
+}
+
+fn uppercase_variations(word: &[char]) -> f64 {
+    let upper = word.iter().filter(|c| c.is_uppercase()).count();
+    let lower = word.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        return 1.0;
+    }
+    let first_only = upper == 1 && word[0].is_uppercase();
+    let last_only = upper == 1 && word[word.len() - 1].is_uppercase();
+    if lower == 0 || first_only || last_only {
+        return 2.0;
+    }
+    (1..=upper.min(lower)).map(|k| binomial(upper + lower, k)).sum()
+}
+
+fn dictionary_matches(chars: &[char], user: &HashMap<String, usize>, out: &mut Vec<Match>) {
+    let lower: Vec<char> = chars.iter().flat_map(|c| c.to_lowercase()).collect();
+    if lower.len() != chars.len() {
+        return;
+    }
+    let n = chars.len();
+    let mut push = |i: usize, j: usize, dict: Dict, rank: usize, l33t: usize, reversed: bool| {
+        let mut log10 = (rank as f64).log10() + uppercase_variations(&chars[i..=j]).log10();
+        if l33t > 0 {
+            log10 += l33t as f64 * 2f64.log10();
+        }
+        if reversed {
+            log10 += 2f64.log10();
+        }
+        out.push(Match {
+            i,
+            j,
+            pattern: Pattern::Dictionary {
+                dict,
+                l33t: l33t > 0,
+                reversed,
+            },
+            log10,
+        });
+    };
+
+    for i in 0..n {
+        for j in (i + 2)..n {
+            let word = &lower[i..=j];
+            let s: String = word.iter().collect();
+            if let Some((d, r)) = lookup(&s, user) {
+                push(i, j, d, r, 0, false);
+            }
+            for table in L33T {
+                if let Some((u, subs)) = unleet(word, table) {
+                    if let Some((d, r)) = lookup(&u, user) {
+                        push(i, j, d, r, subs, false);
+                    }
+                }
+            }
+            let rev: String = word.iter().rev().collect();
+            if rev != s {
+                if let Some((d, r)) = lookup(&rev, user) {
+                    push(i, j, d, r, 0, true);
+                }
+            }
+        }
+    }
+}
+
+// ---------- клавиатура ----------
+
+const QWERTY: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+"),
+    (" qwertyuiop[]\\", " QWERTYUIOP{}|"),
+    (" asdfghjkl;'", " ASDFGHJKL:\""),
+    (" zxcvbnm,./", " ZXCVBNM<>?"),
+];
+/// Средняя степень вершины и число клавиш графа QWERTY.
+const KEYBOARD_DEGREE: f64 = 4.6;
+const KEYBOARD_KEYS: f64 = 94.0;
+
+fn key_pos(c: char) -> Option<(i32, i32, bool)> {
+    QWERTY.iter().enumerate().find_map(|(r, (plain, shifted))| {
+        if c == ' ' {
+            return None;
+        }
+        if let Some(col) = plain.chars().position(|k| k == c) {
+            return Some((r as i32, col as i32, false));
+        }
+        shifted
+            .chars()
+            .position(|k| k == c)
+            .map(|col| (r as i32, col as i32, true))
+    })
+}
+
+/// Номер направления от `a` к соседней клавише `b`, либо `None`, если не соседи.
+fn direction(a: (i32, i32), b: (i32, i32)) -> Option<u8> {
+    match (b.0 - a.0, b.1 - a.1) {
+        (0, -1) => Some(0),
+        (0, 1) => Some(1),
+        (-1, 0) => Some(2),
+        (-1, 1) => Some(3),
+        (1, -1) => Some(4),
+        (1, 0) => Some(5),
+        _ => None,
+    }
+}
+
+fn spatial_matches(chars: &[char], out: &mut Vec<Match>) {
+    let n = chars.len();
+    let mut i = 0;
+    while i + 2 < n {
+        let mut j = i;
+        let mut turns = 0;
+        let mut shifted = 0;
+        let mut last_dir = None;
+        while j + 1 < n {
+            let (Some(a), Some(b)) = (key_pos(chars[j]), key_pos(chars[j + 1])) else {
+                break;
+            };
+            let Some(d) = direction((a.0, a.1), (b.0, b.1)) else {
+                break;
+            };
+            if last_dir != Some(d) {
+                turns += 1;
+                last_dir = Some(d);
+            }
+            if b.2 {
+                shifted += 1;
+            }
+            j += 1;
+        }
+        if j - i + 1 >= 3 {
+            let len = j - i + 1;
+            let mut guesses = 0.0;
+            for l in 2..=len {
+                for t in 1..=turns.min(l - 1) {
+                    guesses += binomial(l - 1, t - 1) * KEYBOARD_KEYS * KEYBOARD_DEGREE.powi(t as i32);
+                }
+            }
+            if shifted > 0 {
+                let unshifted = len - shifted;
+                guesses *= if unshifted == 0 {
+                    2.0
+                } else {
+                    (1..=shifted.min(unshifted)).map(|k| binomial(len, k)).sum()
+                };
+            }
+            out.push(Match {
+                i,
+                j,
+                pattern: Pattern::Spatial { turns },
+                log10: guesses.log10(),
+            });
+            i = j;
+        } else {
+            i += 1;
+        }
+    }
+}
+
+// ---------- последовательности и повторы ----------
+
+fn class(c: char) -> u8 {
+    if c.is_ascii_lowercase() {
+        1
+    } else if c.is_ascii_uppercase() {
+        2
+    } else if c.is_ascii_digit() {
+        3
+    } else {
+        0
+    }
+}
+
+fn sequence_matches(chars: &[char], out: &mut Vec<Match>) {
+    let n = chars.len();
+    let mut i = 0;
+    while i + 2 < n {
+        let delta = chars[i + 1] as i32 - chars[i] as i32;
+        let cls = class(chars[i]);
+        let mut j = i + 1;
+        if cls != 0 && delta.abs() == 1 && class(chars[j]) == cls {
+            while j + 1 < n
+                && class(chars[j + 1]) == cls
+                && chars[j + 1] as i32 - chars[j] as i32 == delta
+            {
+                j += 1;
+            }
+        }
+        if j - i + 1 >= 3 {
+            let first = chars[i];
+            let mut base: f64 = if "aAzZ019".contains(first) {
+                4.0
+            } else if cls == 3 {
+                10.0
+            } else {
+                26.0
+            };
+            if delta < 0 {
+                base *= 2.0;
+            }
+            out.push(Match {
+                i,
+                j,
+                pattern: Pattern::Sequence,
+                log10: (base * (j - i + 1) as f64).log10(),
+            });
+            i = j;
+        } else {
+            i += 1;
+        }
+    }
+}
+
+fn repeat_matches(
+    chars: &[char],
+    user: &HashMap<String, usize>,
+    year: i32,
+    out: &mut Vec<Match>,
+) {
+    let n = chars.len();
+    for i in 0..n {
+        for p in 1..=(n - i) / 2 {
+            let unit = &chars[i..i + p];
+            let mut k = 1;
+            while i + (k + 1) * p <= n && &chars[i + k * p..i + (k + 1) * p] == unit {
+                k += 1;
+            }
+            if k < 2 || (p == 1 && k < 3) {
+                continue;
+            }
+            let base = if p == 1 {
+                cardinality(unit).log10()
+            } else {
+                most_guessable(unit, user, cardinality(unit), year).0
+            };
+            out.push(Match {
+                i,
+                j: i + k * p - 1,
+                pattern: Pattern::Repeat,
+                log10: base + (k as f64).log10(),
+            });
+        }
+    }
+}
+
+// ---------- даты ----------
+
+/// Сколько лет перебирать до `year` от года оценки `now`; не меньше 20.
+fn year_space(year: i32, now: i32) -> f64 {
+    f64::max((year - now).abs() as f64, 20.0)
+}
+
+fn plausible(day: u32, month: u32, year: u32, digits: usize) -> Option<i32> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let year = match digits {
+        2 if year >= 50 => 1900 + year as i32,
+        2 => 2000 + year as i32,
+        _ => year as i32,
+    };
+    (1900..=2050).contains(&year).then_some(year)
+}
+
+/// Пробует все раскладки d/m/y (дд мм гггг, гггг мм дд, мм дд гг …).
+fn parse_date(parts: &[&str]) -> Option<i32> {
+    let nums: Vec<(u32, usize)> = parts
+        .iter()
+        .map(|p| p.parse().ok().map(|v| (v, p.len())))
+        .collect::<Option<_>>()?;
+    let [(a, la), (b, _), (c, lc)] = nums[..] else {
+        return None;
+    };
+    plausible(a, b, c, lc)
+        .or_else(|| plausible(b, a, c, lc))
+        .or_else(|| plausible(c, b, a, la))
+        .or_else(|| plausible(b, c, a, la))
+}
+
+fn date_matches(chars: &[char], now: i32, out: &mut Vec<Match>) {
+    let n = chars.len();
+    for i in 0..n {
+        for j in (i + 3)..n.min(i + 10) {
+            let s: String = chars[i..=j].iter().collect();
+            let len = j - i + 1;
+            let mut year = None;
+            let mut sep = false;
+            if s.chars().all(|c| c.is_ascii_digit()) {
+                if len == 4 {
+                    if let Ok(y) = s.parse::<i32>() {
+                        if (1900..=2050).contains(&y) {
+                            out.push(Match {
+                                i,
+                                j,
+                                pattern: Pattern::Date,
+                                log10: year_space(y, now).log10(),
+                            });
+                        }
+                    }
+                }
+                if len <= 8 {
+                    // все разбиения на три части по 1–4 цифры
+                    for a in 1..len.min(5) {
+                        for b in (a + 1)..len.min(a + 3) {
+                            if len - b > 4 || year.is_some() {
+                                continue;
+                            }
+                            year = parse_date(&[&s[..a], &s[a..b], &s[b..]]);
+                        }
+                    }
+                }
+            } else if len >= 6 {
+                let seps: Vec<char> = s.chars().filter(|c| !c.is_ascii_digit()).collect();
+                if seps.len() == 2 && seps[0] == seps[1] && " -/._\\".contains(seps[0]) {
+                    let parts: Vec<&str> = s.split(seps[0]).collect();
+                    if parts.iter().all(|p| !p.is_empty() && p.len() <= 4) {
+                        year = parse_date(&parts);
+                        sep = true;
+                    }
+                }
+            }
+            if let Some(y) = year {
+                let mut log10 = (365.0 * year_space(y, now)).log10();
+                if sep {
+                    log10 += 4f64.log10();
+                }
+                out.push(Match {
+                    i,
+                    j,
+                    pattern: Pattern::Date,
+                    log10,
+                });
+            }
+        }
+    }
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    (1..=k).fold(1.0, |acc, i| acc * (n - k + i) as f64 / i as f64)
+}
+
+// ---------- подсказки ----------
+
+fn feedback(score: u8, path: &[Match], chars: &[char]) -> (Option<String>, Vec<String>) {
+    if score >= 3 {
+        return (None, Vec::new());
+    }
+    let mut suggestions = vec!["Add another word or two. Uncommon words are better.".to_string()];
+    if chars.len() < 12 {
+        suggestions.push("Use at least 12 characters.".into());
+    }
+    let Some(m) = path
+        .iter()
+        .filter(|m| m.pattern != Pattern::Bruteforce)
+        .max_by_key(|m| m.j - m.i)
+    else {
+        return (None, suggestions);
+    };
+
+    let warning = match &m.pattern {
+        Pattern::Dictionary { dict, l33t, reversed } => {
+            let word = &chars[m.i..=m.j];
+            if word.iter().skip(1).any(|c| c.is_uppercase()) || word[0].is_uppercase() {
+                suggestions.push("Capitalization doesn't help very much.".into());
+            }
+            if *l33t {
+                suggestions.push(
+                    "Predictable substitutions like '@' instead of 'a' don't help very much."
+                        .into(),
+                );
+            }
+            if *reversed {
+                suggestions.push("Reversed words aren't much harder to guess.".into());
+            }
+            let whole = m.i == 0 && m.j + 1 == chars.len();
+            match dict {
+                Dict::Passwords if whole => "This is a very common password.",
+                Dict::Passwords => "This is similar to a commonly used password.",
+                Dict::UserInputs => "Avoid using the site name or your username.",
+                Dict::English if whole => "A word by itself is easy to guess.",
+                Dict::English => "Common words are easy to guess.",
+            }
+        }
+        Pattern::Spatial { turns } => {
+            suggestions.push("Use a longer keyboard pattern with more turns.".into());
+            if *turns == 1 {
+                "Straight rows of keys are easy to guess."
+            } else {
+                "Short keyboard patterns are easy to guess."
+            }
+        }
+        Pattern::Sequence => {
+            suggestions.push("Avoid sequences.".into());
+            "Sequences like abc or 6543 are easy to guess."
+        }
+        Pattern::Repeat => {
+            suggestions.push("Avoid repeated words and characters.".into());
+            "Repeats like \"aaa\" or \"abcabc\" are easy to guess."
+        }
+        Pattern::Date => {
+            suggestions.push("Avoid dates and years that are associated with you.".into());
+            "Dates are often easy to guess."
+        }
+        Pattern::Bruteforce => unreachable!(),
+    };
+    (Some(warning.into()), suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(p: &str) -> u8 {
+        estimate(p, &[]).score
+    }
+
+    #[test]
+    fn weak_patterns_score_low() {
+        for p in ["123456", "password", "qwerty", "P@ssw0rd", "aaaaaaaa", "abcdef"] {
+            assert_eq!(score(p), 0, "{p}");
+        }
+        for p in ["qwertyuiop", "1qaz2wsx", "abcabcabc", "25.12.1984", "19841225"] {
+            assert!(score(p) <= 1, "{p}");
+        }
+        assert!(score("drowssap") <= 1);
+        let r = estimate("github2024", &["github.com", "alice"]);
+        assert!(r.score <= 1);
+        assert_eq!(
+            r.warning.as_deref(),
+            Some("Avoid using the site name or your username.")
+        );
+    }
+
+    #[test]
+    fn strong_passwords_score_high() {
+        assert_eq!(score("k9#Vq!2xLm@8Zr"), 4);
+        assert!(score("tiger-plank-osmosis-vivid") >= 3);
+        let r = estimate("k9#Vq!2xLm@8Zr", &[]);
+        assert!(r.entropy_bits > 60.0);
+        assert!(r.warning.is_none() && r.suggestions.is_empty());
+    }
+
+    #[test]
+    fn feedback_names_the_pattern() {
+        let r = estimate("password", &[]);
+        assert_eq!(r.warning.as_deref(), Some("This is a very common password."));
+        let r = estimate("asdfghjkl", &[]);
+        assert!(r.warning.unwrap().contains("keys"));
+        assert_eq!(estimate("", &[]).score, 0);
+    }
+
+    #[test]
+    fn dates_are_judged_against_the_given_year() {
+        let guesses = |year| estimate_in_year("19.05.2026", &[], year).guesses_log10;
+        assert!(guesses(2026) < guesses(1960));
+        assert_eq!(guesses(2026), guesses(2030));
+    }
+}